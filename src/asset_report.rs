@@ -0,0 +1,210 @@
+//! Build, sign and submit the BTC and BRC-20 assets of a committee for 'committee_assets.update_assets'.
+use std::collections::HashMap;
+use codec::Encode;
+use sp_core::H256 as Hash;
+use crate::query::committee_assets::{all_concerned_brc20, brc20_decimals, committee_assets_consensus};
+use crate::submit::committee_assets::update_assets_with_route;
+use crate::submit::SubmitRoute;
+use crate::BoolSubClient;
+
+pub const BTC_DECIMALS: u8 = 8;
+
+/// Parse the decimal `amount` (ie. "0.5") to the integer amount with `decimals`.
+pub fn parse_amount(amount: &str, decimals: u8) -> Result<u128, String> {
+    let amount = amount.trim();
+    let (int, frac) = amount.split_once('.').unwrap_or((amount, ""));
+    if (int.is_empty() && frac.is_empty()) || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(format!("invalid amount: {amount}"));
+    }
+    if frac.len() > decimals as usize {
+        return Err(format!("amount {amount} exceeds {decimals} decimals"));
+    }
+    let digits = format!("{int}{frac:0<width$}", width = decimals as usize);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits.parse::<u128>().map_err(|_| format!("amount {amount} overflows"))
+}
+
+/// Validate the BRC-20 `balances` (tick, decimal amount) against the concerned ticks, return the
+/// integer balances of all concerned ticks in their order, the ticks are matched case-insensitively
+/// and the missing ones are reported as zero.
+pub fn normalise_brc20(
+    concerned: &[Vec<u8>],
+    decimals: &HashMap<Vec<u8>, u8>,
+    balances: &[(String, String)],
+) -> Result<Vec<(Vec<u8>, u128)>, String> {
+    let mut amounts: HashMap<Vec<u8>, u128> = HashMap::new();
+    for (tick, amount) in balances {
+        let Some(concerned_tick) = concerned.iter().find(|t| t.eq_ignore_ascii_case(tick.as_bytes())) else {
+            return Err(format!("brc20 {tick} is not concerned"));
+        };
+        let decimal = decimals.get(concerned_tick).copied().ok_or(format!("no decimals for brc20 {tick}"))?;
+        if amounts.insert(concerned_tick.clone(), parse_amount(amount, decimal)?).is_some() {
+            return Err(format!("duplicated brc20 {tick}"));
+        }
+    }
+    Ok(concerned.iter().map(|tick| (tick.clone(), amounts.get(tick).copied().unwrap_or_default())).collect())
+}
+
+/// Assets report of a committee, submitted once both `sender_sig` and `cmt_sig` are present.
+/// The signatures are produced over `signing_payload`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssetReport {
+    pub cid: u32,
+    pub block_number: u32,
+    pub btc_asset: u128,
+    pub brc20_assets: Vec<(Vec<u8>, u128)>,
+    pub fork_id: u8,
+    pub sender_pk: Vec<u8>,
+    pub sender_sig: Option<Vec<u8>>,
+    pub cmt_sig: Option<Vec<u8>>,
+}
+
+impl AssetReport {
+    /// Read the concerned BRC-20 and the last consensus of `cid`, then validate and normalise the balances.
+    /// `btc` and the amounts of `brc20` are decimal strings, ie. "0.5" BTC.
+    pub async fn prepare(
+        sub_client: &BoolSubClient,
+        cid: u32,
+        fork_id: u8,
+        block_number: u32,
+        btc: &str,
+        brc20: &[(String, String)],
+    ) -> Result<Self, String> {
+        let block_hash = sub_client.block_hash_or_latest(None).await.map_err(|e| e.to_string())?;
+        check_block_number(sub_client, cid, block_number, Some(block_hash)).await?;
+        let concerned = all_concerned_brc20(sub_client, Some(block_hash)).await.map_err(|e| e.to_string())?.unwrap_or_default();
+        let mut decimals = HashMap::new();
+        for tick in &concerned {
+            if let Some(decimal) = brc20_decimals(sub_client, tick.clone(), Some(block_hash)).await.map_err(|e| e.to_string())? {
+                decimals.insert(tick.clone(), decimal);
+            }
+        }
+        Ok(AssetReport {
+            cid,
+            block_number,
+            btc_asset: parse_amount(btc, BTC_DECIMALS)?,
+            brc20_assets: normalise_brc20(&concerned, &decimals, brc20)?,
+            fork_id,
+            ..Default::default()
+        })
+    }
+
+    /// Message signed by the sender and the committee for 'update_assets', the SCALE encoding of the reported
+    /// call arguments in call order: (cid, block_number, btc_asset, brc20_assets).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        (self.cid, self.block_number, self.btc_asset, &self.brc20_assets).encode()
+    }
+
+    pub fn with_sender_sig(mut self, sender_pk: Vec<u8>, sender_sig: Vec<u8>) -> Self {
+        self.sender_pk = sender_pk;
+        self.sender_sig = Some(sender_sig);
+        self
+    }
+
+    pub fn with_cmt_sig(mut self, cmt_sig: Vec<u8>) -> Self {
+        self.cmt_sig = Some(cmt_sig);
+        self
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.sender_sig.is_some() && self.cmt_sig.is_some()
+    }
+
+    /// Submit the report by `route` if both signatures are present and `block_number` is not stale.
+    pub async fn submit(&self, sub_client: &BoolSubClient, route: SubmitRoute) -> Result<Hash, String> {
+        let (Some(sender_sig), Some(cmt_sig)) = (&self.sender_sig, &self.cmt_sig) else {
+            return Err("assets report is not signed by both sender and committee".to_string());
+        };
+        check_block_number(sub_client, self.cid, self.block_number, None).await?;
+        update_assets_with_route(
+            sub_client,
+            route,
+            self.cid,
+            self.block_number,
+            self.btc_asset,
+            self.brc20_assets.clone(),
+            self.sender_pk.clone(),
+            sender_sig.clone(),
+            cmt_sig.clone(),
+            self.fork_id,
+        )
+        .await
+    }
+}
+
+// reject `block_number` below the last assets consensus of committee
+async fn check_block_number(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    block_number: u32,
+    at_block: Option<Hash>,
+) -> Result<(), String> {
+    let consensus = committee_assets_consensus(sub_client, cid, at_block).await.map_err(|e| e.to_string())?;
+    check_stale(cid, block_number, consensus.map(|(_, last, _)| last))
+}
+
+// the report of the block of `last` consensus joins it, only the earlier blocks are stale
+fn check_stale(cid: u32, block_number: u32, last: Option<u64>) -> Result<(), String> {
+    match last {
+        Some(last) if (block_number as u64) < last => {
+            Err(format!("stale block number {block_number} for committee {cid}, last consensus at {last}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn test_normalise_assets() {
+    assert_eq!(parse_amount("1.5", 8), Ok(150_000_000));
+    assert_eq!(parse_amount("0.00000001", 8), Ok(1));
+    assert_eq!(parse_amount(".5", 1), Ok(5));
+    assert_eq!(parse_amount("0", 18), Ok(0));
+    assert!(parse_amount("0.000000001", 8).is_err());
+    assert!(parse_amount("1.2.3", 8).is_err());
+    assert!(parse_amount("-1", 8).is_err());
+    assert!(parse_amount("", 8).is_err());
+
+    let concerned = vec![b"ordi".to_vec(), b"sats".to_vec()];
+    let decimals = HashMap::from([(b"ordi".to_vec(), 18), (b"sats".to_vec(), 0)]);
+    let balances = vec![("SATS".to_string(), "100".to_string())];
+    assert_eq!(
+        normalise_brc20(&concerned, &decimals, &balances),
+        Ok(vec![(b"ordi".to_vec(), 0), (b"sats".to_vec(), 100)])
+    );
+    let duplicated = vec![("sats".to_string(), "1".to_string()), ("Sats".to_string(), "2".to_string())];
+    assert!(normalise_brc20(&concerned, &decimals, &duplicated).is_err());
+    assert!(normalise_brc20(&concerned, &decimals, &[("pepe".to_string(), "1".to_string())]).is_err());
+}
+
+#[test]
+fn test_signing_payload() {
+    let report = AssetReport {
+        cid: 1,
+        block_number: 2,
+        btc_asset: 3,
+        brc20_assets: vec![(b"ordi".to_vec(), 4)],
+        ..Default::default()
+    }
+    .with_sender_sig(vec![5], vec![6])
+    .with_cmt_sig(vec![7]);
+    let mut expected = vec![1, 0, 0, 0, 2, 0, 0, 0];
+    expected.extend([3].iter().chain(&[0; 15]));
+    // compact length of the one item, then (compact length ++ "ordi", u128)
+    expected.extend([4, 16, b'o', b'r', b'd', b'i', 4]);
+    expected.extend([0; 15]);
+    assert_eq!(report.signing_payload(), expected);
+}
+
+#[test]
+fn test_check_stale() {
+    assert_eq!(check_stale(1, 100, None), Ok(()));
+    assert_eq!(check_stale(1, 101, Some(100)), Ok(()));
+    assert_eq!(check_stale(1, 100, Some(100)), Ok(()));
+    assert_eq!(
+        check_stale(1, 99, Some(100)),
+        Err("stale block number 99 for committee 1, last consensus at 100".to_string())
+    );
+}
@@ -0,0 +1,385 @@
+//! Inspect the BTC taproots and scripts of committees in channel storage, ie. decode them to Bitcoin addresses.
+use sp_core::H256 as Hash;
+use crate::bool::runtime_types::pallet_channel::types::{BtcScriptPair, CmtType, TaprootPair, TaprootType};
+use crate::query::channel::{bound_script, btc_committee_type, escape_taproot};
+use crate::query::committee::committees;
+use crate::BoolSubClient;
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum BtcNetwork {
+    #[default]
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl BtcNetwork {
+    pub fn hrp(&self) -> &'static str {
+        match self {
+            BtcNetwork::Mainnet => "bc",
+            BtcNetwork::Testnet | BtcNetwork::Signet => "tb",
+            BtcNetwork::Regtest => "bcrt",
+        }
+    }
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATORS: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk = 1u32;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ *value as u32;
+        for (i, generator) in GENERATORS.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// Encode the segwit address of witness `program`, bech32 for version 0 and bech32m for others (BIP-173, BIP-350).
+pub fn segwit_address(network: BtcNetwork, version: u8, program: &[u8]) -> Result<String, String> {
+    if version > 16 || !(2..=40).contains(&program.len()) || (version == 0 && ![20, 32].contains(&program.len())) {
+        return Err(format!("invalid witness program of version {version}: 0x{}", hex::encode(program)));
+    }
+    let mut data = vec![version];
+    // convert 8 bits groups to 5 bits groups with padding
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for byte in program {
+        acc = (acc << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push(((acc << (5 - bits)) & 31) as u8);
+    }
+    let hrp = network.hrp();
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values.extend(&data);
+    values.extend([0u8; 6]);
+    let polymod = bech32_polymod(&values) ^ if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    data.extend((0..6).map(|i| ((polymod >> (5 * (5 - i))) & 31) as u8));
+    Ok(format!("{hrp}1{}", data.iter().map(|d| BECH32_CHARSET[*d as usize] as char).collect::<String>()))
+}
+
+/// Return the x-only key of the 32 bytes x-only or 33 bytes compressed public `key`.
+pub fn xonly_key(key: &[u8]) -> Result<[u8; 32], String> {
+    let x = match key.len() {
+        32 => key,
+        33 => &key[1..],
+        _ => return Err(format!("invalid public key: 0x{}", hex::encode(key))),
+    };
+    let mut res = [0u8; 32];
+    res.copy_from_slice(x);
+    Ok(res)
+}
+
+/// P2TR address of the taproot output key `tweaked_pk`.
+pub fn taproot_address(network: BtcNetwork, tweaked_pk: &[u8]) -> Result<String, String> {
+    segwit_address(network, 1, &xonly_key(tweaked_pk)?)
+}
+
+fn tagged_hash(tag: &str, msg: &[u8]) -> [u8; 32] {
+    let tag = sp_core::sha2_256(tag.as_bytes());
+    sp_core::sha2_256(&[&tag[..], &tag[..], msg].concat())
+}
+
+/// BIP-341 'TapTweak' hash of `internal_key` and the merkle root of the script tree.
+pub fn tap_tweak_hash(internal_key: &[u8], merkle_root: Option<[u8; 32]>) -> Result<[u8; 32], String> {
+    let mut msg = xonly_key(internal_key)?.to_vec();
+    if let Some(root) = merkle_root {
+        msg.extend(root);
+    }
+    Ok(tagged_hash("TapTweak", &msg))
+}
+
+/// Return the x-only output key of `internal_key` tweaked by `tweak`, ie. `P + tweak * G` with the even `P`.
+pub fn tweak_public_key(internal_key: &[u8], tweak: &[u8]) -> Result<[u8; 32], String> {
+    let mut compressed = [2u8; 33];
+    compressed[1..].copy_from_slice(&xonly_key(internal_key)?);
+    let mut pk = secp256k1::PublicKey::parse_compressed(&compressed).map_err(|e| format!("invalid internal key: {e:?}"))?;
+    let tweak = secp256k1::SecretKey::parse_slice(tweak).map_err(|e| format!("invalid tweak: {e:?}"))?;
+    pk.tweak_add_assign(&tweak).map_err(|e| format!("tweak public key failed for: {e:?}"))?;
+    xonly_key(&pk.serialize_compressed())
+}
+
+/// Split the concatenated x-only keys of a leaf.
+pub fn split_xonly_keys(keys: &[u8]) -> Result<Vec<[u8; 32]>, String> {
+    if keys.len() % 32 != 0 {
+        return Err(format!("invalid x-only keys of {} bytes: 0x{}", keys.len(), hex::encode(keys)));
+    }
+    keys.chunks(32).map(xonly_key).collect()
+}
+
+/// Script leaves of `TaprootType`, the key sets and parameters are in the order of the variant fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaprootScriptTree {
+    pub kind: String,
+    // x-only keys of the multisig and escape leaves, one key set per leaf
+    pub leaf_keys: Vec<Vec<[u8; 32]>>,
+    // threshold of the multisig leaf
+    pub threshold: Option<u32>,
+    // timelocks of the escape leaves
+    pub escape_timelocks: Vec<u32>,
+}
+
+impl TaprootScriptTree {
+    /// The tree has script leaves, so the tap tweak hash must commit to a merkle root.
+    pub fn has_scripts(&self) -> bool {
+        !self.leaf_keys.is_empty()
+    }
+}
+
+impl TryFrom<&TaprootType> for TaprootScriptTree {
+    type Error = String;
+
+    fn try_from(taproot_type: &TaprootType) -> Result<Self, Self::Error> {
+        let (kind, leaf_keys, threshold, escape_timelocks) = match taproot_type.clone() {
+            TaprootType::None => ("None", vec![], None, vec![]),
+            TaprootType::Empty => ("Empty", vec![], None, vec![]),
+            TaprootType::EscapeTimeLock(key, lock) => ("EscapeTimeLock", vec![key], None, vec![lock]),
+            TaprootType::MultiSign(keys) => ("MultiSign", vec![keys], None, vec![]),
+            TaprootType::MultiSignEscapeLock(keys, lock) => ("MultiSignEscapeLock", vec![keys], None, vec![lock]),
+            TaprootType::MultiSignEscapeLock2(keys, escape_keys, lock) => {
+                ("MultiSignEscapeLock2", vec![keys, escape_keys], None, vec![lock])
+            }
+            TaprootType::MultiSignEscapeEnforceLock(keys, escape_keys, lock, enforce_keys, enforce_lock, enforce_lock2) => (
+                "MultiSignEscapeEnforceLock",
+                vec![keys, escape_keys, enforce_keys],
+                None,
+                vec![lock, enforce_lock, enforce_lock2],
+            ),
+            TaprootType::MultiSignThresholdEscapeLock2(keys, escape_keys, threshold, lock) => {
+                ("MultiSignThresholdEscapeLock2", vec![keys, escape_keys], Some(threshold), vec![lock])
+            }
+            TaprootType::MultiSignThresholdEscapeEnforceLock(
+                keys,
+                escape_keys,
+                threshold,
+                lock,
+                enforce_keys,
+                enforce_lock,
+                enforce_lock2,
+            ) => (
+                "MultiSignThresholdEscapeEnforceLock",
+                vec![keys, escape_keys, enforce_keys],
+                Some(threshold),
+                vec![lock, enforce_lock, enforce_lock2],
+            ),
+        };
+        Ok(TaprootScriptTree {
+            kind: kind.to_string(),
+            leaf_keys: leaf_keys.iter().map(|keys| split_xonly_keys(keys)).collect::<Result<_, _>>()?,
+            threshold,
+            escape_timelocks,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaprootDetails {
+    pub cid: u32,
+    pub internal_key: [u8; 32],
+    pub tap_tweak_hash: Vec<u8>,
+    pub tweaked_pk: [u8; 32],
+    pub address: String,
+    pub script_tree: TaprootScriptTree,
+    // the tap tweak hash commits to a script tree, ie. it's not the tweak of the internal key only
+    pub commits_scripts: bool,
+}
+
+/// Decode the escape taproot of channel storage with the address on `network`.
+pub fn inspect_taproot(pair: &TaprootPair, network: BtcNetwork) -> Result<TaprootDetails, String> {
+    Ok(TaprootDetails {
+        cid: pair.cid,
+        internal_key: xonly_key(&pair.internal_key)?,
+        tap_tweak_hash: pair.tap_tweak_hash.clone(),
+        tweaked_pk: xonly_key(&pair.tweaked_pk)?,
+        address: taproot_address(network, &pair.tweaked_pk)?,
+        script_tree: TaprootScriptTree::try_from(&pair.taproot_type)?,
+        commits_scripts: tap_tweak_hash(&pair.internal_key, None)?.as_slice() != pair.tap_tweak_hash.as_slice(),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptDetails {
+    pub cid: u32,
+    pub script_hash: Vec<u8>,
+    // P2WSH address if the script hash is the 32 bytes sha256 of a witness script, None if the hash type is unknown
+    pub address: Option<String>,
+    pub script_tree: TaprootScriptTree,
+}
+
+/// Decode the bound script of channel storage with the address on `network`.
+pub fn inspect_script(pair: &BtcScriptPair, network: BtcNetwork) -> Result<ScriptDetails, String> {
+    let script_tree = TaprootScriptTree::try_from(&pair.script_type)?;
+    let address = match (pair.script_hash.len(), script_tree.has_scripts()) {
+        (32, true) => Some(segwit_address(network, 0, &pair.script_hash)?),
+        _ => None,
+    };
+    Ok(ScriptDetails { cid: pair.cid, script_hash: pair.script_hash.clone(), address, script_tree })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaprootVerification {
+    // the tap tweak hash commits to the internal key only, or to the given merkle root
+    Verified,
+    // the keys and tweak are consistent, but the script tree committed by the tap tweak hash is not checked
+    // without its merkle root
+    ScriptTreeUnverified,
+}
+
+/// Verify the escape taproot is built on the aggregated key of committee:
+/// the internal key is `committee_pubkey`, the tweaked key is the internal key tweaked by the tap tweak hash,
+/// and the tap tweak hash commits to `merkle_root` if it's provided, otherwise it commits to a script tree
+/// only if the taproot type has script leaves, which is `TaprootVerification::ScriptTreeUnverified`.
+pub fn verify_escape_taproot(
+    pair: &TaprootPair,
+    committee_pubkey: &[u8],
+    merkle_root: Option<[u8; 32]>,
+) -> Result<TaprootVerification, String> {
+    if xonly_key(&pair.internal_key)? != xonly_key(committee_pubkey)? {
+        return Err(format!(
+            "internal key 0x{} is not the committee key 0x{}",
+            hex::encode(&pair.internal_key),
+            hex::encode(committee_pubkey)
+        ));
+    }
+    let (committed, verification) = match merkle_root {
+        Some(root) => {
+            let committed = tap_tweak_hash(&pair.internal_key, Some(root))?.as_slice() == pair.tap_tweak_hash.as_slice();
+            (committed, TaprootVerification::Verified)
+        }
+        None => {
+            let commits_scripts = tap_tweak_hash(&pair.internal_key, None)?.as_slice() != pair.tap_tweak_hash.as_slice();
+            let verification = if commits_scripts {
+                TaprootVerification::ScriptTreeUnverified
+            } else {
+                TaprootVerification::Verified
+            };
+            (commits_scripts == TaprootScriptTree::try_from(&pair.taproot_type)?.has_scripts(), verification)
+        }
+    };
+    if !committed {
+        return Err(format!("tap tweak hash 0x{} mismatches the script tree", hex::encode(&pair.tap_tweak_hash)));
+    }
+    if tweak_public_key(&pair.internal_key, &pair.tap_tweak_hash)? != xonly_key(&pair.tweaked_pk)? {
+        return Err(format!("tweaked key 0x{} mismatches the internal key and tweak", hex::encode(&pair.tweaked_pk)));
+    }
+    Ok(verification)
+}
+
+/// BTC data of a committee in channel storage read at one block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BtcCommitteeDetails {
+    pub cid: u32,
+    pub cmt_type: Option<CmtType>,
+    pub escape_taproot: Option<TaprootDetails>,
+    pub bound_script: Option<ScriptDetails>,
+    // result of `verify_escape_taproot` with the committee pubkey, None if there's no escape taproot
+    pub escape_verified: Option<Result<TaprootVerification, String>>,
+}
+
+/// Read and decode the BTC committee type, escape taproot and bound script of committee `cid`.
+/// The escape taproot is verified against `merkle_root` of its script tree if it's known by the caller.
+pub async fn inspect_btc_committee(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    network: BtcNetwork,
+    merkle_root: Option<[u8; 32]>,
+    at_block: Option<Hash>,
+) -> Result<BtcCommitteeDetails, String> {
+    let block_hash = sub_client.block_hash_or_latest(at_block).await.map_err(|e| e.to_string())?;
+    let cmt_type = btc_committee_type(sub_client, cid, Some(block_hash)).await.map_err(|e| e.to_string())?;
+    let taproot = escape_taproot(sub_client, cid, Some(block_hash)).await.map_err(|e| e.to_string())?;
+    let script = bound_script(sub_client, cid, Some(block_hash)).await.map_err(|e| e.to_string())?;
+    let committee = committees(sub_client, cid, Some(block_hash)).await.map_err(|e| e.to_string())?;
+    let escape_verified = taproot.as_ref().map(|pair| match &committee {
+        Some(committee) => verify_escape_taproot(pair, &committee.pubkey, merkle_root),
+        None => Err(format!("committee {cid} not found")),
+    });
+    Ok(BtcCommitteeDetails {
+        cid,
+        cmt_type: cmt_type.map(|t| t.cmt_type),
+        escape_taproot: taproot.map(|pair| inspect_taproot(&pair, network)).transpose()?,
+        bound_script: script.map(|pair| inspect_script(&pair, network)).transpose()?,
+        escape_verified,
+    })
+}
+
+#[test]
+fn test_taproot_address() {
+    let program = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+    assert_eq!(segwit_address(BtcNetwork::Mainnet, 0, &program).unwrap(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+
+    // BIP-86 m/86'/0'/0'/0/0
+    let internal_key = hex::decode("cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115").unwrap();
+    let tweak = tap_tweak_hash(&internal_key, None).unwrap();
+    let tweaked_pk = tweak_public_key(&internal_key, &tweak).unwrap();
+    assert_eq!(hex::encode(tweaked_pk), "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c");
+    assert_eq!(
+        taproot_address(BtcNetwork::Mainnet, &tweaked_pk).unwrap(),
+        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+    );
+    assert_eq!(
+        taproot_address(BtcNetwork::Regtest, &tweaked_pk).unwrap(),
+        "bcrt1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqvg32hk"
+    );
+
+    // key path only taproot
+    let committee_pubkey = [&[2u8][..], &internal_key].concat();
+    let mut pair = TaprootPair {
+        cid: 1,
+        internal_key: internal_key.clone(),
+        tap_tweak_hash: tweak.to_vec(),
+        tweaked_pk: tweaked_pk.to_vec(),
+        taproot_type: TaprootType::Empty,
+    };
+    assert_eq!(verify_escape_taproot(&pair, &committee_pubkey, None), Ok(TaprootVerification::Verified));
+    assert!(verify_escape_taproot(&pair, &tweaked_pk, None).is_err());
+    assert!(!inspect_taproot(&pair, BtcNetwork::Testnet).unwrap().commits_scripts);
+
+    // the escape leaf must be committed by the tweak
+    pair.taproot_type = TaprootType::EscapeTimeLock(tweaked_pk.to_vec(), 144);
+    assert!(verify_escape_taproot(&pair, &committee_pubkey, None).is_err());
+    let merkle_root = [7u8; 32];
+    let tweak = tap_tweak_hash(&internal_key, Some(merkle_root)).unwrap();
+    pair.tap_tweak_hash = tweak.to_vec();
+    pair.tweaked_pk = tweak_public_key(&internal_key, &tweak).unwrap().to_vec();
+    // the script tree is only verified with its merkle root
+    assert_eq!(verify_escape_taproot(&pair, &committee_pubkey, None), Ok(TaprootVerification::ScriptTreeUnverified));
+    assert_eq!(verify_escape_taproot(&pair, &committee_pubkey, Some(merkle_root)), Ok(TaprootVerification::Verified));
+    assert!(verify_escape_taproot(&pair, &committee_pubkey, Some([8u8; 32])).is_err());
+    let details = inspect_taproot(&pair, BtcNetwork::Testnet).unwrap();
+    assert!(details.commits_scripts);
+    assert_eq!(details.script_tree.leaf_keys, vec![vec![tweaked_pk]]);
+    assert_eq!(details.script_tree.escape_timelocks, vec![144]);
+}
+
+#[test]
+fn test_script_details() {
+    let keys = [[1u8; 32], [2u8; 32]].concat();
+    assert_eq!(split_xonly_keys(&keys).unwrap(), vec![[1u8; 32], [2u8; 32]]);
+    assert!(split_xonly_keys(&keys[1..]).is_err());
+
+    let script_hash = sp_core::sha2_256(b"script").to_vec();
+    let mut pair = BtcScriptPair { cid: 1, script_hash: script_hash.clone(), script_type: TaprootType::MultiSign(keys) };
+    let details = inspect_script(&pair, BtcNetwork::Mainnet).unwrap();
+    assert_eq!(details.address, Some(segwit_address(BtcNetwork::Mainnet, 0, &script_hash).unwrap()));
+    assert_eq!(details.script_tree.leaf_keys.len(), 1);
+    pair.script_hash = script_hash[..20].to_vec();
+    assert_eq!(inspect_script(&pair, BtcNetwork::Mainnet).unwrap().address, None);
+    pair.script_type = TaprootType::MultiSign(vec![1; 33]);
+    assert!(inspect_script(&pair, BtcNetwork::Mainnet).is_err());
+}
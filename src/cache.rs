@@ -0,0 +1,147 @@
+//! Read-through cache of storage values for `SubClient`.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sp_core::H256 as Hash;
+
+/// max number of values cached at explicit blocks, all of them are dropped when it's reached.
+const MAX_BLOCK_VALUES: usize = 4096;
+/// max interval between runtime version checks of cached reads, if no new block checked it.
+pub const SPEC_VERSION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+struct CachedValue {
+    value: Option<Vec<u8>>,
+    fetched_at: Instant,
+    ttl: Option<Duration>,
+}
+
+/// Cache of raw storage values, only storage items configured by `with_item` are cached.
+/// Values of the latest state expire after the ttl of their item, or when they change in a new block
+/// (see `SubClient::invalidate_cache_by_block`), values at explicit blocks never change.
+/// All of them are dropped after a runtime upgrade, the runtime version is checked by every new block,
+/// or by a cached read if it's not checked in `SPEC_VERSION_CHECK_INTERVAL`.
+#[derive(Clone, Debug, Default)]
+pub struct StorageCache {
+    // ttl of cached storage items by (pallet, entry), None for caching until runtime upgrade
+    items: HashMap<(String, String), Option<Duration>>,
+    latest: HashMap<Vec<u8>, CachedValue>,
+    at_blocks: HashMap<(Vec<u8>, Hash), Option<Vec<u8>>>,
+    spec_version: Option<u32>,
+    spec_checked_at: Option<Instant>,
+}
+
+impl StorageCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache the storage items which are read on nearly every operation.
+    pub fn with_default_items() -> Self {
+        Self::new()
+            .with_item("Configs", "DeviceHeartbeatInterval", Some(Duration::from_secs(60)))
+            .with_item("Configs", "SimpleSign", Some(Duration::from_secs(60)))
+            .with_item("Committee", "EpochConfig", Some(Duration::from_secs(60)))
+            .with_item("EVMChainId", "ChainId", None)
+    }
+
+    /// Cache storage item `pallet.entry`, None `ttl` caches it until runtime upgrade.
+    pub fn with_item(mut self, pallet: &str, entry: &str, ttl: Option<Duration>) -> Self {
+        self.items.insert((pallet.to_string(), entry.to_string()), ttl);
+        self
+    }
+
+    /// Return the ttl of storage item if it's cached.
+    pub fn item_ttl(&self, pallet: &str, entry: &str) -> Option<Option<Duration>> {
+        self.items.get(&(pallet.to_string(), entry.to_string())).copied()
+    }
+
+    /// Return the ttl of the storage item of full storage `key` if it's cached.
+    pub fn key_ttl(&self, key: &[u8]) -> Option<Option<Duration>> {
+        self.items
+            .iter()
+            .find(|((pallet, entry), _)| {
+                key.starts_with(&[sp_core::twox_128(pallet.as_bytes()), sp_core::twox_128(entry.as_bytes())].concat())
+            })
+            .map(|(_, ttl)| *ttl)
+    }
+
+    /// Return the cached value of `key`, Some(None) means the storage is empty.
+    pub fn get(&self, key: &[u8], at_block: Option<Hash>) -> Option<Option<Vec<u8>>> {
+        match at_block {
+            Some(block) => self.at_blocks.get(&(key.to_vec(), block)).cloned(),
+            None => self
+                .latest
+                .get(key)
+                .filter(|v| v.ttl.map_or(true, |ttl| v.fetched_at.elapsed() < ttl))
+                .map(|v| v.value.clone()),
+        }
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, value: Option<Vec<u8>>, ttl: Option<Duration>, at_block: Option<Hash>) {
+        match at_block {
+            Some(block) => {
+                if self.at_blocks.len() >= MAX_BLOCK_VALUES {
+                    self.at_blocks.clear();
+                }
+                self.at_blocks.insert((key, block), value);
+            }
+            None => {
+                self.latest.insert(key, CachedValue { value, fetched_at: Instant::now(), ttl });
+            }
+        }
+    }
+
+    /// Keys of cached latest values, which should be checked in new blocks.
+    pub fn latest_keys(&self) -> Vec<Vec<u8>> {
+        self.latest.keys().cloned().collect()
+    }
+
+    pub fn invalidate(&mut self, key: &[u8]) {
+        self.latest.remove(key);
+    }
+
+    /// Drop all values if the runtime spec version changed, return true if dropped.
+    pub fn check_spec_version(&mut self, spec_version: u32) -> bool {
+        let changed = self.spec_version.map_or(false, |v| v != spec_version);
+        if changed {
+            self.clear();
+        }
+        self.spec_version = Some(spec_version);
+        self.spec_checked_at = Some(Instant::now());
+        changed
+    }
+
+    /// Whether the runtime version should be checked before reading the cache.
+    pub fn spec_version_check_due(&self) -> bool {
+        self.spec_checked_at.map_or(true, |at| at.elapsed() >= SPEC_VERSION_CHECK_INTERVAL)
+    }
+
+    pub fn clear(&mut self) {
+        self.latest.clear();
+        self.at_blocks.clear();
+    }
+}
+
+#[test]
+fn test_key_ttl() {
+    let cache = StorageCache::with_default_items();
+    let prefix = [sp_core::twox_128(b"Committee"), sp_core::twox_128(b"EpochConfig")].concat();
+    assert_eq!(cache.key_ttl(&prefix), Some(Some(Duration::from_secs(60))));
+    let prefix = [sp_core::twox_128(b"EVMChainId"), sp_core::twox_128(b"ChainId")].concat();
+    assert_eq!(cache.key_ttl(&prefix), Some(None));
+    let key = [&sp_core::twox_128(b"Committee")[..], &sp_core::twox_128(b"Committees")[..], &[0u8; 4][..]].concat();
+    assert_eq!(cache.key_ttl(&key), None);
+}
+
+#[test]
+fn test_spec_version_check() {
+    let mut cache = StorageCache::with_default_items();
+    assert!(cache.spec_version_check_due());
+    assert!(!cache.check_spec_version(1));
+    assert!(!cache.spec_version_check_due());
+    cache.insert(vec![1], Some(vec![2]), None, None);
+    assert!(!cache.check_spec_version(1));
+    assert_eq!(cache.get(&[1], None), Some(Some(vec![2])));
+    assert!(cache.check_spec_version(2));
+    assert_eq!(cache.get(&[1], None), None);
+}
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use anyhow::Result;
 use bnk_node_primitives::AccountId20;
-use codec::{Compact, Encode};
-use sp_core::H256 as Hash;
+use codec::{Compact, Decode, Encode};
+use sp_core::{H160, H256 as Hash};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
@@ -16,7 +16,13 @@ use subxt::{
 };
 use subxt::config::extrinsic_params::BaseExtrinsicParamsBuilder;
 use subxt::tx::{Signer, SubmittableExtrinsic};
-use crate::bool::runtime_types::ethereum::transaction::{EIP1559Transaction, TransactionV2 as EvmTransaction, TransactionAction};
+use subxt::blocks::ExtrinsicEvents;
+use subxt::metadata::{DecodeWithMetadata, StorageEntryType};
+use futures::{Stream, StreamExt};
+use crate::bool::runtime_types::ethereum::transaction::TransactionV2 as EvmTransaction;
+use crate::evm::{EvmTransactionMessage, SignedEvmTransaction};
+use crate::cache::StorageCache;
+use crate::storage::{StorageBatch, StorageReadProof};
 
 #[derive(Clone, Debug)]
 pub enum BoolConfig {}
@@ -32,6 +38,17 @@ impl Config for BoolConfig {
     type ExtrinsicParams = PolkadotExtrinsicParams<Self>;
 }
 
+/// Which state the queries read when no `at_block` is given.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ReadConsistency {
+    // the latest best block
+    #[default]
+    Best,
+    // the latest finalized block
+    Finalized,
+    AtBlock(Hash),
+}
+
 #[derive(Clone)]
 pub struct SubClient<C: Config, P: Signer<C> + Clone> {
     pub ws_url: String,
@@ -44,6 +61,10 @@ pub struct SubClient<C: Config, P: Signer<C> + Clone> {
     pub call_cache: Arc<RwLock<HashMap<u32, (Box<dyn TxPayload + Send + Sync>, bool, Vec<u8>, u128)>>>,
     // milliseconds, default 10000 milllis(10 seconds)
     pub warn_time: u128,
+    // read-through cache of storage values, disabled by default
+    pub cache: Option<Arc<RwLock<StorageCache>>>,
+    // state read by queries without 'at_block', default the best block
+    pub read_consistency: ReadConsistency,
 }
 
 impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
@@ -62,6 +83,8 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
             cache_size_for_call: cache_size_for_call.unwrap_or(10),
             call_cache: Arc::new(RwLock::new(HashMap::new())),
             warn_time: warn_time.unwrap_or(10000),
+            cache: None,
+            read_consistency: ReadConsistency::Best,
         }
     }
 
@@ -85,6 +108,8 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
             cache_size_for_call: cache_size_for_call.unwrap_or(10),
             call_cache: Arc::new(RwLock::new(HashMap::new())),
             warn_time: warn_time.unwrap_or(10000),
+            cache: None,
+            read_consistency: ReadConsistency::Best,
         })
     }
 
@@ -95,6 +120,19 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         call: Call,
         nonce: Option<u32>,
     ) -> Result<Hash, Error> {
+        self.submit_extrinsic_with_signer_and_watch_events(call, nonce)
+            .await
+            .map(|events| events.extrinsic_hash())
+    }
+
+    /// Same as `submit_extrinsic_with_signer_and_watch`, but return all the events of the extrinsic.
+    pub async fn submit_extrinsic_with_signer_and_watch_events<
+        Call: TxPayload + 'static + Send + Sync,
+    >(
+        &self,
+        call: Call,
+        nonce: Option<u32>,
+    ) -> Result<ExtrinsicEvents<BoolConfig>, Error> {
         let call = Box::new(call);
         let timer =   Instant::now();
         self.check_client_runtime_version_and_update().await?;
@@ -103,48 +141,10 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         let mut call_cache = self.call_cache.write().await;
         let client = self.client.read().await;
         let signer = self.signer.as_ref().ok_or_else(|| Error::Other("empty sk to sign and submit tx".to_string()))?;
-        let account_id = signer.account_id();
 
-        let target_nonce = if let Some(nonce) = nonce {
-            nonce
-        } else {
-            let chain_nonce = client.tx().account_nonce(account_id).await? as u32;
-            // clear cache for lower nonce, retain 10 nonce due to 'chain_nonce' can roll back
-            let oldest_nonce = std::cmp::max(chain_nonce, 10);
-            let old_nonce = call_cache.keys().filter(|v| v < &&(oldest_nonce - 10)).cloned().collect::<Vec<_>>();
-            for key in old_nonce {
-                log::trace!(target: "subxt::call_cache", "remove key {:?}", key);
-                call_cache.remove(&key);
-            }
-            if chain_nonce >= *inner_nonce {
-                chain_nonce
-            } else {
-                // Some errors occurred. ie. some tx with nonce not submit to chain seccessfully.
-                if *inner_nonce - chain_nonce > self.cache_size_for_call {
-                    log::warn!(target: "subxt", "Some errors occurred to nonce inner {}, chain {}", *inner_nonce, chain_nonce);
-                    for key in chain_nonce..*inner_nonce {
-                        if let Some((inner_call, by_evm, _, _)) = call_cache.get(&key) {
-                            let tx = if *by_evm {
-                                client.tx().create_unsigned(inner_call)?
-                            } else {
-                                client.tx().create_signed_with_nonce(
-                                    inner_call,
-                                    signer,
-                                    key,
-                                    Default::default()
-                                )?
-                            };
-                            let tx_hash = tx
-                                .submit()
-                                .await;
-                            log::warn!(target: "subxt", "re-submit call with nonce: {}, res: {:?}", key, tx_hash);
-                        } else {
-                            log::warn!(target: "subxt", "re-submit call not find nonce: {} in cache", key);
-                        }
-                    }
-                }
-                *inner_nonce
-            }
+        let target_nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.next_nonce(&client, signer, *inner_nonce, &mut call_cache).await?,
         };
         let tx: subxt::tx::SubmittableExtrinsic<BoolConfig, OnlineClient<BoolConfig>> = client.tx().create_signed_with_nonce(
             &call,
@@ -152,7 +152,7 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
             target_nonce,
             Default::default(),
         )?;
-        let tx_hash = match tx
+        let tx_events = match tx
             .submit_and_watch()
             .await?
             .wait_for_in_block()
@@ -163,14 +163,51 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
                 *inner_nonce = target_nonce + 1;
                 // update call_cache
                 call_cache.insert(target_nonce, (call, false, vec![], 0));
-                tx.wait_for_success().await?.extrinsic_hash()
+                tx.wait_for_success().await?
             },
             Err(e) => return Err(e)
         };
         if timer.elapsed().as_millis() > self.warn_time {
             log::warn!(target: "subxt", "submit_extrinsic_with_signer_and_watch exceed warn_time: {} millis", timer.elapsed().as_millis());
         }
-        Ok(tx_hash)
+        Ok(tx_events)
+    }
+
+    /// Sign and submit `call` with the next nonce, return the progress of the extrinsic without waiting for it,
+    /// so that several calls can be submitted with consecutive nonces before any of them is in block.
+    pub async fn submit_extrinsic_with_signer_and_progress<
+        Call: TxPayload + 'static + Send + Sync,
+    >(
+        &self,
+        call: Call,
+        nonce: Option<u32>,
+    ) -> Result<TxProgress<BoolConfig, OnlineClient<BoolConfig>>, Error> {
+        let call = Box::new(call);
+        let timer = Instant::now();
+        self.check_client_runtime_version_and_update().await?;
+
+        let mut inner_nonce = self.inner_nonce.write().await;
+        let mut call_cache = self.call_cache.write().await;
+        let client = self.client.read().await;
+        let signer = self.signer.as_ref().ok_or_else(|| Error::Other("empty sk to sign and submit tx".to_string()))?;
+
+        let target_nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.next_nonce(&client, signer, *inner_nonce, &mut call_cache).await?,
+        };
+        let progress = client
+            .tx()
+            .create_signed_with_nonce(&call, signer, target_nonce, Default::default())?
+            .submit_and_watch()
+            .await?;
+        log::debug!(target: "subxt::nonce", "inner_nonce {}, insert cache for nonce: {}", target_nonce + 1, target_nonce);
+        *inner_nonce = target_nonce + 1;
+        // update call_cache
+        call_cache.insert(target_nonce, (call, false, vec![], 0));
+        if timer.elapsed().as_millis() > self.warn_time {
+            log::warn!(target: "subxt", "submit_extrinsic_with_signer_and_progress exceed warn_time: {} millis", timer.elapsed().as_millis());
+        }
+        Ok(progress)
     }
 
     pub async fn submit_extrinsic_with_signer_without_watch<
@@ -188,53 +225,10 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         let mut call_cache = self.call_cache.write().await;
         let client = self.client.read().await;
         let signer = self.signer.as_ref().ok_or_else(|| Error::Other("empty sk to sign and submit tx".to_string()))?;
-        let account_id = signer.account_id();
 
-        let target_nonce = if let Some(nonce) = nonce {
-            nonce
-        } else {
-            let chain_nonce = client.tx().account_nonce(account_id).await? as u32;
-            // clear cache for lower nonce
-            let old_nonce = call_cache.keys().filter(|v| v < &&chain_nonce).cloned().collect::<Vec<_>>();
-            for key in old_nonce {
-                log::trace!(target: "subxt::call_cache", "remove key {:?}", key);
-                call_cache.remove(&key);
-            }
-            if chain_nonce >= *inner_nonce {
-                chain_nonce
-            } else {
-                // Some errors occurred. ie. some tx with nonce not submit to chain seccessfully.
-                if *inner_nonce - chain_nonce >= self.cache_size_for_call {
-                    log::warn!(target: "subxt", "Some errors occurred to nonce inner {}, chain {}", *inner_nonce, chain_nonce);
-                    for key in chain_nonce ..*inner_nonce {
-                        if let Some((inner_call, by_evm, input, tip)) = call_cache.get_mut(&key) {
-                            let tx = if *by_evm {
-                                let mut eip1995_tx = <ethereum::EIP1559Transaction as codec::Decode>::decode(&mut input.as_slice())?;
-                                eip1995_tx.max_priority_fee_per_gas = eip1995_tx.max_priority_fee_per_gas + sp_core::U256::from(*tip + 100u128);
-                                let evm_tx = self.build_eip1559_tx_to_v2(eip1995_tx).map_err(|e| Error::Other(e))?;
-                                let evm_call = crate::bool::tx().ethereum().transact(evm_tx);
-                                client.tx().create_unsigned(&evm_call)?
-                            } else {
-                                client.tx().create_signed_with_nonce(
-                                    inner_call,
-                                    signer,
-                                    key,
-                                    BaseExtrinsicParamsBuilder::new().tip(*tip + 100),
-                                )?
-                            };
-                            let tx_hash = tx
-                                .submit()
-                                .await;
-                            log::warn!(target: "subxt", "re-submit call with nonce: {}, tip: {:?}, res: {:?}", key, *tip + 100, tx_hash);
-                            //update tip
-                            *tip += 100;
-                        } else {
-                            log::warn!(target: "subxt", "re-submit call not find nonce: {} in cache", key);
-                        }
-                    }
-                }
-                *inner_nonce
-            }
+        let target_nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.next_nonce(&client, signer, *inner_nonce, &mut call_cache).await?,
         };
         let tx = client.tx().create_signed_with_nonce(
             &call,
@@ -261,6 +255,59 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         Ok(tx_hash)
     }
 
+    // Nonce of the next tx signed by `signer`. If 'inner_nonce' is ahead of chain by 'cache_size_for_call',
+    // some txs were not submitted to chain successfully, re-submit the cached calls with a higher tip.
+    async fn next_nonce(
+        &self,
+        client: &OnlineClient<BoolConfig>,
+        signer: &BoolSigner<BoolConfig>,
+        inner_nonce: u32,
+        call_cache: &mut HashMap<u32, (Box<dyn TxPayload + Send + Sync>, bool, Vec<u8>, u128)>,
+    ) -> Result<u32, Error> {
+        let chain_nonce = client.tx().account_nonce(signer.account_id()).await? as u32;
+        // clear cache for lower nonce, retain 10 nonce due to 'chain_nonce' can roll back
+        let oldest_nonce = std::cmp::max(chain_nonce, 10);
+        let old_nonce = call_cache.keys().filter(|v| v < &&(oldest_nonce - 10)).cloned().collect::<Vec<_>>();
+        for key in old_nonce {
+            log::trace!(target: "subxt::call_cache", "remove key {:?}", key);
+            call_cache.remove(&key);
+        }
+        if chain_nonce >= inner_nonce {
+            return Ok(chain_nonce);
+        }
+        // Some errors occurred. ie. some tx with nonce not submit to chain seccessfully.
+        if inner_nonce - chain_nonce >= self.cache_size_for_call {
+            log::warn!(target: "subxt", "Some errors occurred to nonce inner {}, chain {}", inner_nonce, chain_nonce);
+            for key in chain_nonce..inner_nonce {
+                if let Some((inner_call, by_evm, input, tip)) = call_cache.get_mut(&key) {
+                    let tx = if *by_evm {
+                        let mut eip1995_tx = <ethereum::EIP1559Transaction as codec::Decode>::decode(&mut input.as_slice())?;
+                        eip1995_tx.max_priority_fee_per_gas = eip1995_tx.max_priority_fee_per_gas + sp_core::U256::from(*tip + 100u128);
+                        let evm_tx = self.build_eip1559_tx_to_v2(eip1995_tx).map_err(|e| Error::Other(e))?;
+                        let evm_call = crate::bool::tx().ethereum().transact(evm_tx);
+                        client.tx().create_unsigned(&evm_call)?
+                    } else {
+                        client.tx().create_signed_with_nonce(
+                            inner_call,
+                            signer,
+                            key,
+                            BaseExtrinsicParamsBuilder::new().tip(*tip + 100),
+                        )?
+                    };
+                    let tx_hash = tx
+                        .submit()
+                        .await;
+                    log::warn!(target: "subxt", "re-submit call with nonce: {}, tip: {:?}, res: {:?}", key, *tip + 100, tx_hash);
+                    //update tip
+                    *tip += 100;
+                } else {
+                    log::warn!(target: "subxt", "re-submit call not find nonce: {} in cache", key);
+                }
+            }
+        }
+        Ok(inner_nonce)
+    }
+
     pub async fn signed_tx_encode_to_bytes<Call: TxPayload + 'static + Send + Sync>(
         &self,
         call: Call,
@@ -270,53 +317,10 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         let mut call_cache = self.call_cache.write().await;
         let client = self.client.read().await;
         let signer = self.signer.as_ref().ok_or_else(|| Error::Other("empty sk to sign and submit tx".to_string()))?;
-        let account_id = signer.account_id();
 
-        let target_nonce = if let Some(nonce) = nonce {
-            nonce
-        } else {
-            let chain_nonce = client.tx().account_nonce(account_id).await? as u32;
-            // clear cache for lower nonce
-            let old_nonce = call_cache.keys().filter(|v| v < &&chain_nonce).cloned().collect::<Vec<_>>();
-            for key in old_nonce {
-                log::trace!(target: "subxt::call_cache", "remove key {:?}", key);
-                call_cache.remove(&key);
-            }
-            if chain_nonce >= *inner_nonce {
-                chain_nonce
-            } else {
-                // Some errors occurred. ie. some tx with nonce not submit to chain seccessfully.
-                if *inner_nonce - chain_nonce >= self.cache_size_for_call {
-                    log::warn!(target: "subxt", "Some errors occurred to nonce inner {}, chain {}", *inner_nonce, chain_nonce);
-                    for key in chain_nonce ..*inner_nonce {
-                        if let Some((inner_call, by_evm, input, tip)) = call_cache.get_mut(&key) {
-                            let tx = if *by_evm {
-                                let mut eip1995_tx = <ethereum::EIP1559Transaction as codec::Decode>::decode(&mut input.as_slice())?;
-                                eip1995_tx.max_priority_fee_per_gas = eip1995_tx.max_priority_fee_per_gas + sp_core::U256::from(*tip + 100u128);
-                                let evm_tx = self.build_eip1559_tx_to_v2(eip1995_tx).map_err(|e| Error::Other(e))?;
-                                let evm_call = crate::bool::tx().ethereum().transact(evm_tx);
-                                client.tx().create_unsigned(&evm_call)?
-                            } else {
-                                client.tx().create_signed_with_nonce(
-                                    inner_call,
-                                    signer,
-                                    key,
-                                    BaseExtrinsicParamsBuilder::new().tip(*tip + 100),
-                                )?
-                            };
-                            let tx_hash = tx
-                                .submit()
-                                .await;
-                            log::warn!(target: "subxt", "re-submit call with nonce: {}, tip: {:?}, res: {:?}", key, *tip + 100, tx_hash);
-                            //update tip
-                            *tip += 100;
-                        } else {
-                            log::warn!(target: "subxt", "re-submit call not find nonce: {} in cache", key);
-                        }
-                    }
-                }
-                *inner_nonce
-            }
+        let target_nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.next_nonce(&client, signer, *inner_nonce, &mut call_cache).await?,
         };
 
         // 1. Validate this call against the current node metadata if the call comes
@@ -412,14 +416,28 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         at_block: Option<Hash>,
     ) -> Result<Option<F::Target>, Error> {
         let timer =   Instant::now();
-        self.check_client_runtime_version_and_update().await?;
-        let storage_client = self.client.read().await.storage();
-        let res = match at_block {
-            Some(block) => {
-                storage_client.at(block).fetch(&store_query).await
-            },
-            None => {
-                storage_client.at_latest().await?.fetch(&store_query).await
+        let cached_item = self.cached_item(&store_query).await;
+        match cached_item {
+            Some((cache, _)) => self.check_runtime_version_for_cache(cache).await?,
+            None => self.check_client_runtime_version_and_update().await?,
+        }
+        let at_block = self.resolve_read_block(at_block).await?;
+        let res = if let Some((cache, ttl)) = cached_item {
+            let metadata = self.client.read().await.metadata();
+            let key = crate::storage::storage_address_bytes(&store_query, &metadata)?;
+            self.fetch_raw_cached(cache, key, ttl, at_block)
+                .await?
+                .map(|data| crate::storage::decode_storage_value(&store_query, &data, &metadata))
+                .transpose()
+        } else {
+            let storage_client = self.client.read().await.storage();
+            match at_block {
+                Some(block) => {
+                    storage_client.at(block).fetch(&store_query).await
+                },
+                None => {
+                    storage_client.at_latest().await?.fetch(&store_query).await
+                }
             }
         };
         if timer.elapsed().as_millis() > self.warn_time {
@@ -428,6 +446,79 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         res
     }
 
+    // the storage cache and ttl of `store_query` if its storage item is cached
+    async fn cached_item<F: StorageAddress>(&self, store_query: &F) -> Option<(&RwLock<StorageCache>, Option<std::time::Duration>)> {
+        let cache = self.cache.as_ref()?;
+        let ttl = cache.read().await.item_ttl(store_query.pallet_name(), store_query.entry_name())?;
+        Some((cache.as_ref(), ttl))
+    }
+
+    // cache hits need no rpc call, the runtime version is checked by `invalidate_cache_by_block` on every new block,
+    // and here only if it's not checked in `SPEC_VERSION_CHECK_INTERVAL`
+    async fn check_runtime_version_for_cache(&self, cache: &RwLock<StorageCache>) -> Result<(), Error> {
+        if cache.read().await.spec_version_check_due() {
+            self.check_client_runtime_version_and_update().await?;
+        }
+        Ok(())
+    }
+
+    // read the raw value of storage `key` through the storage cache
+    async fn fetch_raw_cached(
+        &self,
+        cache: &RwLock<StorageCache>,
+        key: Vec<u8>,
+        ttl: Option<std::time::Duration>,
+        at_block: Option<Hash>,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(value) = cache.read().await.get(&key, at_block) {
+            return Ok(value);
+        }
+        let value = self.client.read().await.rpc().storage(&key, at_block).await?.map(|data| data.0);
+        cache.write().await.insert(key, value.clone(), ttl, at_block);
+        Ok(value)
+    }
+
+    /// Drop the cached values changed in block `block_hash`, and all of them after a runtime upgrade.
+    pub async fn invalidate_cache_by_block(&self, block_hash: Hash) -> Result<(), Error> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        // all cached values are dropped by the check after a runtime upgrade
+        self.check_client_runtime_version_and_update().await?;
+        let client = self.client.read().await;
+        let keys = cache.read().await.latest_keys();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let parent = client
+            .rpc()
+            .header(Some(block_hash))
+            .await?
+            .ok_or_else(|| Error::Other(format!("header of {block_hash:?} not found")))?
+            .parent_hash;
+        let change_sets = client.rpc().query_storage(keys.iter().map(|k| &k[..]), parent, Some(block_hash)).await?;
+        let mut cache = cache.write().await;
+        // the change set of 'parent' contains the values at it, only changes in 'block_hash' matter
+        for change_set in change_sets.into_iter().filter(|c| c.block == block_hash) {
+            for (key, _) in change_set.changes {
+                log::debug!(target: "subxt", "invalidate cached storage: 0x{}", hex::encode(&key.0));
+                cache.invalidate(&key.0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Invalidate cached values by every new best block, it only returns when the block subscription failed.
+    pub async fn watch_cache_invalidation(&self) -> Result<(), Error> {
+        let mut blocks = self.client.read().await.blocks().subscribe_best().await?;
+        while let Some(block) = blocks.next().await {
+            if let Err(e) = self.invalidate_cache_by_block(block?.hash()).await {
+                log::warn!(target: "subxt", "invalidate storage cache failed for: {:?}", e);
+            }
+        }
+        Err(Error::Other("best block subscription closed".to_string()))
+    }
+
     pub async fn query_storage_value_iter<F: StorageAddress<IsIterable = Yes> + 'static>(
         &self,
         store_query: F,
@@ -436,6 +527,7 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
     ) -> Result<Vec<(StorageKey, F::Target)>, Error> {
         let timer = Instant::now();
         self.check_client_runtime_version_and_update().await?;
+        let at_block = self.resolve_read_block(at_block).await?;
         let storage_client = self.client.read().await.storage();
         let mut iter = match at_block {
             Some(block) => {
@@ -455,20 +547,266 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         Ok(values)
     }
 
+    /// Iter map storage like `query_storage_value_iter`, with the storage key decoded as `K` by the metadata hashers.
+    pub async fn query_storage_key_value_iter<K: Decode, F: StorageAddress<IsIterable = Yes> + 'static>(
+        &self,
+        store_query: F,
+        page_sise: u32,
+        at_block: Option<Hash>,
+    ) -> Result<Vec<(K, F::Target)>, Error> {
+        let (pallet, entry) = (store_query.pallet_name().to_string(), store_query.entry_name().to_string());
+        let values = self.query_storage_value_iter(store_query, page_sise, at_block).await?;
+        let metadata = self.client.read().await.metadata();
+        values
+            .into_iter()
+            .map(|(key, value)| {
+                crate::storage::decode_storage_key::<K>(&metadata, &pallet, &entry, &key.0).map(|key| (key, value))
+            })
+            .collect()
+    }
+
+    /// Stream entries of map storage page by page at one block, instead of collecting all of them as `query_storage_value_iter`.
+    /// Entries are yielded in storage key order, pass the last key handled as `start_key` to resume the iteration after it.
+    /// The latest block is pinned if `at_block` is None, so all pages are read at the same state.
+    pub async fn query_storage_value_stream<F: StorageAddress<IsIterable = Yes> + 'static>(
+        &self,
+        store_query: F,
+        page_sise: u32,
+        start_key: Option<Vec<u8>>,
+        at_block: Option<Hash>,
+    ) -> Result<impl Stream<Item = Result<(StorageKey, F::Target), Error>>, Error> {
+        self.check_client_runtime_version_and_update().await?;
+        let at_block = self.block_hash_or_latest(at_block).await?;
+        let client = self.client.read().await.clone();
+        let metadata = client.metadata();
+        let (pallet, entry) = (store_query.pallet_name(), store_query.entry_name());
+        let value_ty = match metadata
+            .pallet_by_name(pallet)
+            .and_then(|p| p.storage())
+            .and_then(|s| s.entry_by_name(entry))
+            .map(|e| e.entry_type())
+        {
+            Some(StorageEntryType::Map { value_ty, .. }) => *value_ty,
+            _ => return Err(Error::Other(format!("storage {pallet}.{entry} is not a map"))),
+        };
+        let prefix = crate::storage::storage_address_bytes(&store_query, &metadata)?;
+        let warn_time = self.warn_time;
+
+        // state: (cursor, fetched entries of current page, no more pages)
+        let state = (start_key, VecDeque::new(), false);
+        Ok(futures::stream::try_unfold(state, move |(mut cursor, mut entries, mut finished)| {
+            let (client, metadata, prefix) = (client.clone(), metadata.clone(), prefix.clone());
+            async move {
+                while entries.is_empty() && !finished {
+                    let timer = Instant::now();
+                    let keys = client
+                        .rpc()
+                        .storage_keys_paged(&prefix, page_sise, cursor.as_deref(), Some(at_block))
+                        .await?;
+                    finished = keys.len() < page_sise as usize;
+                    match keys.last() {
+                        Some(last) => cursor = Some(last.0.clone()),
+                        None => break,
+                    }
+                    let mut page = Vec::with_capacity(keys.len());
+                    for change_set in client.rpc().query_storage_at(keys.iter().map(|k| &k.0[..]), Some(at_block)).await? {
+                        for (key, data) in change_set.changes {
+                            if let Some(data) = data {
+                                let value = <F::Target as DecodeWithMetadata>::decode_with_metadata(&mut &data.0[..], value_ty, &metadata)?;
+                                page.push((StorageKey(key.0), value));
+                            }
+                        }
+                    }
+                    page.sort_by(|a, b| a.0.0.cmp(&b.0.0));
+                    entries.extend(page);
+                    if timer.elapsed().as_millis() > warn_time {
+                        log::warn!(target: "subxt", "query_storage_value_stream page exceed warn_time: {} millis", timer.elapsed().as_millis());
+                    }
+                }
+                Ok(entries.pop_front().map(|entry| (entry, (cursor, entries, finished))))
+            }
+        }))
+    }
+
+    /// Fetch a tuple of storage addresses at one block in a single 'state_queryStorageAt' request,
+    /// ie. `(committees(cid), candidate_links(cid, fork))` returns `(Option<Committee>, Option<Vec<u16>>)`.
+    pub async fn query_storage_batch<B: StorageBatch>(
+        &self,
+        batch: B,
+        at_block: Option<Hash>,
+    ) -> Result<B::Output, Error> {
+        let timer = Instant::now();
+        let at_block = self.block_hash_or_latest(at_block).await?;
+        let keys = batch.keys(&self.client.read().await.metadata())?;
+        // (ttl, cached value) of the keys of cached storage items
+        let mut cached = vec![(None, None); keys.len()];
+        if let Some(cache) = &self.cache {
+            let cache = cache.read().await;
+            for (key, (ttl, value)) in keys.iter().zip(cached.iter_mut()) {
+                *ttl = cache.key_ttl(key);
+                *value = ttl.and_then(|_| cache.get(key, Some(at_block)));
+            }
+        }
+        let missing = keys.iter().zip(&cached).filter(|(_, (_, value))| value.is_none()).map(|(key, _)| &key[..]).collect::<Vec<_>>();
+        match &self.cache {
+            Some(cache) if missing.is_empty() => self.check_runtime_version_for_cache(cache).await?,
+            _ => self.check_client_runtime_version_and_update().await?,
+        }
+        let client = self.client.read().await;
+        let metadata = client.metadata();
+        let mut changes = HashMap::new();
+        if !missing.is_empty() {
+            for change_set in client.rpc().query_storage_at(missing, Some(at_block)).await? {
+                for (key, data) in change_set.changes {
+                    changes.insert(key.0, data.map(|data| data.0));
+                }
+            }
+        }
+        let mut values = Vec::with_capacity(keys.len());
+        for (key, (ttl, value)) in keys.iter().zip(cached) {
+            let value = match (ttl, value) {
+                (_, Some(value)) => value,
+                (Some(ttl), None) => {
+                    let value = changes.get(key).cloned().flatten();
+                    if let Some(cache) = &self.cache {
+                        cache.write().await.insert(key.clone(), value.clone(), ttl, Some(at_block));
+                    }
+                    value
+                }
+                (None, None) => changes.get(key).cloned().flatten(),
+            };
+            values.push(value);
+        }
+        if timer.elapsed().as_millis() > self.warn_time {
+            log::warn!(target: "subxt", "query_storage_batch exceed warn_time: {} millis", timer.elapsed().as_millis());
+        }
+        batch.decode(values, &metadata)
+    }
+
+    /// Fetch all raw (key, value) pairs of storage under `prefix` at block `at_block`, in storage key order.
+    pub async fn query_storage_pairs(
+        &self,
+        prefix: Vec<u8>,
+        page_sise: u32,
+        at_block: Hash,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+        let timer = Instant::now();
+        self.check_client_runtime_version_and_update().await?;
+        let client = self.client.read().await;
+        let mut pairs = Vec::new();
+        let mut start_key: Option<Vec<u8>> = None;
+        loop {
+            let keys = client
+                .rpc()
+                .storage_keys_paged(&prefix, page_sise, start_key.as_deref(), Some(at_block))
+                .await?;
+            let Some(last) = keys.last() else {
+                break;
+            };
+            start_key = Some(last.0.clone());
+            for change_set in client.rpc().query_storage_at(keys.iter().map(|k| &k.0[..]), Some(at_block)).await? {
+                for (key, data) in change_set.changes {
+                    if let Some(data) = data {
+                        pairs.push((key.0, data.0));
+                    }
+                }
+            }
+            if keys.len() < page_sise as usize {
+                break;
+            }
+        }
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        if timer.elapsed().as_millis() > self.warn_time {
+            log::warn!(target: "subxt", "query_storage_pairs exceed warn_time: {} millis", timer.elapsed().as_millis());
+        }
+        Ok(pairs)
+    }
+
+    /// Return `at_block` or pin the block of `read_consistency`, so that a group of queries read the same state.
+    pub async fn block_hash_or_latest(&self, at_block: Option<Hash>) -> Result<Hash, Error> {
+        match self.resolve_read_block(at_block).await? {
+            Some(block) => Ok(block),
+            None => self.client.read().await.blocks().at_latest().await.map(|block| block.hash()),
+        }
+    }
+
+    /// Block hash to read for `consistency`, None means the latest best block.
+    /// ie. pass `block_for_read(ReadConsistency::Finalized)` as `at_block` of a query to read finalized state for one call.
+    pub async fn block_for_read(&self, consistency: ReadConsistency) -> Result<Option<Hash>, Error> {
+        match consistency {
+            ReadConsistency::Best => Ok(None),
+            ReadConsistency::Finalized => self.client.read().await.rpc().finalized_head().await.map(Some),
+            ReadConsistency::AtBlock(block) => Ok(Some(block)),
+        }
+    }
+
+    /// `at_block` if it's given, otherwise the block of client `read_consistency`.
+    pub async fn resolve_read_block(&self, at_block: Option<Hash>) -> Result<Option<Hash>, Error> {
+        match at_block {
+            Some(block) => Ok(Some(block)),
+            None => self.block_for_read(self.read_consistency).await,
+        }
+    }
+
+    /// Same as `query_storage`, but also return the read proof of the value with the state root of the block,
+    /// which can be checked by `storage::verify_read_proof` without trusting the node.
+    pub async fn query_storage_with_proof<F: StorageAddress<IsFetchable = Yes>>(
+        &self,
+        store_query: F,
+        at_block: Option<Hash>,
+    ) -> Result<(Option<F::Target>, StorageReadProof), Error> {
+        let timer = Instant::now();
+        self.check_client_runtime_version_and_update().await?;
+        let block_hash = self.block_hash_or_latest(at_block).await?;
+        let client = self.client.read().await;
+        let metadata = client.metadata();
+        let key = crate::storage::storage_address_bytes(&store_query, &metadata)?;
+        let state_root = client
+            .rpc()
+            .header(Some(block_hash))
+            .await?
+            .ok_or_else(|| Error::Other(format!("header of {block_hash:?} not found")))?
+            .state_root;
+        let read_proof = client.rpc().read_proof([&key[..]], Some(block_hash)).await?;
+        let proof = read_proof.proof.into_iter().map(|node| node.0).collect::<Vec<_>>();
+        // decode from the proof, so the value always matches the proof
+        let value = crate::storage::verify_read_proof_raw(state_root, &key, proof.clone())?
+            .map(|data| crate::storage::decode_storage_value(&store_query, &data, &metadata))
+            .transpose()?;
+        if timer.elapsed().as_millis() > self.warn_time {
+            log::warn!(target: "subxt", "query_storage_with_proof exceed warn_time: {} millis", timer.elapsed().as_millis());
+        }
+        Ok((value, StorageReadProof { block_hash, state_root, key, proof }))
+    }
+
     pub async fn query_storage_or_default<F: StorageAddress<IsFetchable = Yes, IsDefaultable = Yes>>(
         &self,
         store_query: F,
         at_block: Option<Hash>,
     ) -> Result<F::Target, Error> {
         let timer =   Instant::now();
-        self.check_client_runtime_version_and_update().await?;
-        let storage_client = self.client.read().await.storage();
-        let res = match at_block {
-            Some(block) => {
-                storage_client.at(block).fetch_or_default(&store_query).await
-            },
-            None => {
-                storage_client.at_latest().await?.fetch_or_default(&store_query).await
+        let cached_item = self.cached_item(&store_query).await;
+        match cached_item {
+            Some((cache, _)) => self.check_runtime_version_for_cache(cache).await?,
+            None => self.check_client_runtime_version_and_update().await?,
+        }
+        let at_block = self.resolve_read_block(at_block).await?;
+        let res = if let Some((cache, ttl)) = cached_item {
+            let metadata = self.client.read().await.metadata();
+            let key = crate::storage::storage_address_bytes(&store_query, &metadata)?;
+            match self.fetch_raw_cached(cache, key, ttl, at_block).await? {
+                Some(data) => crate::storage::decode_storage_value(&store_query, &data, &metadata),
+                None => crate::storage::decode_storage_default(&store_query, &metadata),
+            }
+        } else {
+            let storage_client = self.client.read().await.storage();
+            match at_block {
+                Some(block) => {
+                    storage_client.at(block).fetch_or_default(&store_query).await
+                },
+                None => {
+                    storage_client.at_latest().await?.fetch_or_default(&store_query).await
+                }
             }
         };
         if timer.elapsed().as_millis() > self.warn_time {
@@ -491,6 +829,21 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         res
     }
 
+    pub async fn call_runtime_api(
+        &self,
+        function: &str,
+        call_parameters: Vec<u8>,
+        at_block: Option<Hash>,
+    ) -> Result<Vec<u8>, Error> {
+        let timer =   Instant::now();
+        self.check_client_runtime_version_and_update().await?;
+        let res = self.client.read().await.rpc().state_call(function, Some(&call_parameters), at_block).await;
+        if timer.elapsed().as_millis() > self.warn_time {
+            log::warn!(target: "subxt", "call_runtime_api exceed warn_time: {} millis", timer.elapsed().as_millis());
+        }
+        res.map(|bytes| bytes.0)
+    }
+
     pub async fn query_account_nonce(&self) -> Option<u32> {
         let timer =   Instant::now();
         self.check_client_runtime_version_and_update().await.ok()?;
@@ -516,45 +869,81 @@ impl SubClient<BoolConfig, BoolSigner<BoolConfig>> {
         res
     }
 
+    /// Sign an EIP-1559 tx which calls `to` with `input` by the client signer, and submit it by `ethereum.transact`.
+    /// The evm tx shares the same 'inner_nonce' and 'call_cache' with substrate extrinsics.
+    pub async fn submit_evm_call_with_signer(
+        &self,
+        to: H160,
+        input: Vec<u8>,
+        need_watch_res: bool,
+        nonce: Option<u32>,
+    ) -> Result<Hash, String> {
+        let timer = Instant::now();
+        let chain_id = crate::query::ethereum::evm_chain_id(self, None)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("get evm chain failed".to_string())?;
+
+        let mut inner_nonce = self.inner_nonce.write().await;
+        let mut call_cache = self.call_cache.write().await;
+        let client = self.client.read().await;
+        let signer = self.signer.as_ref().ok_or_else(|| "empty sk to sign and submit tx".to_string())?;
+        let target_nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.next_nonce(&client, signer, *inner_nonce, &mut call_cache).await.map_err(|e| e.to_string())?,
+        };
+        let tx = ethereum::EIP1559Transaction {
+            chain_id,
+            nonce: sp_core::U256::from(target_nonce),
+            max_priority_fee_per_gas: sp_core::U256::from(1500000000u128),
+            max_fee_per_gas: sp_core::U256::from(4500000000u128),
+            gas_limit: sp_core::U256::from(500000u128),
+            action: ethereum::TransactionAction::Call(to),
+            value: sp_core::U256::from(0u128),
+            input,
+            access_list: Default::default(),
+            odd_y_parity: false,
+            r: Default::default(),
+            s: Default::default()
+        };
+        let transaction = self.build_eip1559_tx_to_v2(tx.clone())?;
+        let evm_call = crate::bool::tx().ethereum().transact(transaction.clone());
+        let submittable = client.tx().create_unsigned(&evm_call).map_err(|e| e.to_string())?;
+        let res = if need_watch_res {
+            match submittable.submit_and_watch().await.map_err(crate::handle_custom_error)?.wait_for_in_block().await {
+                Ok(tx) => tx.wait_for_success().await.map(|e| e.extrinsic_hash()).map_err(crate::handle_custom_error),
+                Err(e) => Err(crate::handle_custom_error(e)),
+            }
+        } else {
+            submittable.submit().await.map_err(crate::handle_custom_error)
+        };
+        let tx_hash = match res {
+            Ok(hash) => {
+                log::debug!(target: "subxt::nonce", "inner_nonce {}, insert cache for nonce: {}", target_nonce + 1, target_nonce);
+                *inner_nonce = target_nonce + 1;
+                // update call_cache
+                call_cache.insert(
+                    target_nonce,
+                    (Box::new(evm_call), true, tx.encode(), 0)
+                );
+                hash
+            },
+            Err(e) => return Err(e)
+        };
+        if timer.elapsed().as_millis() > self.warn_time {
+            log::warn!(target: "subxt", "submit_evm_call_with_signer exceed warn_time: {} millis", timer.elapsed().as_millis());
+        }
+        Ok(tx_hash)
+    }
+
     pub fn build_eip1559_tx_to_v2(&self, tx: ethereum::EIP1559Transaction) -> Result<EvmTransaction, String> {
         let tx = ethereum::EIP1559TransactionMessage::from(tx);
-        let sk = self.signer.clone().ok_or("Not set bool client signer")?.signer().serialize();
-        let secret = secp256k1::SecretKey::parse(&sk).map_err(|e| format!("Parse bool signer sk failed for: {:?}", e))?;
-        let signing_message = secp256k1::Message::parse_slice(&tx.hash()[..])
-            .map_err(|e| e.to_string())?;
-        let (signature, recid) = secp256k1::sign(&signing_message, &secret);
-        let rs = signature.serialize();
-        let r = Hash::from_slice(&rs[0..32]);
-        let s = Hash::from_slice(&rs[32..64]);
-        Ok(
-            EvmTransaction::EIP1559(EIP1559Transaction {
-                chain_id: tx.chain_id,
-                nonce: crate::bool::runtime_types::primitive_types::U256(
-                    tx.nonce.0,
-                ),
-                max_priority_fee_per_gas: crate::bool::runtime_types::primitive_types::U256(
-                    tx.max_priority_fee_per_gas.0
-                ),
-                max_fee_per_gas: crate::bool::runtime_types::primitive_types::U256(
-                    tx.max_fee_per_gas.0
-                ),
-                gas_limit: crate::bool::runtime_types::primitive_types::U256(
-                    tx.gas_limit.0
-                ),
-                action: match tx.action {
-                    ethereum::TransactionAction::Call(addr) => TransactionAction::Call(addr),
-                    _ => return Err(format!("Invalid evm tx action: {:?}", tx.action))
-                },
-                value: crate::bool::runtime_types::primitive_types::U256(
-                    tx.value.0
-                ),
-                input: tx.input,
-                access_list: vec![],
-                odd_y_parity: recid.serialize() != 0,
-                r,
-                s,
-            })
-        )
+        self.sign_evm_transaction(EvmTransactionMessage::EIP1559(tx)).map(|signed| signed.transaction)
+    }
+
+    /// Sign a legacy, EIP-2930 or EIP-1559 evm tx by the client signer.
+    pub fn sign_evm_transaction(&self, tx: EvmTransactionMessage) -> Result<SignedEvmTransaction, String> {
+        crate::evm::sign_evm_transaction(self.signer.as_ref().ok_or("Not set bool client signer")?, tx)
     }
 }
 
@@ -580,10 +969,24 @@ impl<C: Config, P: Signer<C> + Clone> SubClient<C, P> {
                 cache_size_for_call: cache_size_for_call.unwrap_or(10),
                 call_cache: Arc::new(RwLock::new(HashMap::new())),
                 warn_time: warn_time.unwrap_or(10000),
+                cache: None,
+                read_consistency: ReadConsistency::Best,
             }
         )
     }
 
+    /// Set the state read by queries without 'at_block'.
+    pub fn with_read_consistency(mut self, read_consistency: ReadConsistency) -> Self {
+        self.read_consistency = read_consistency;
+        self
+    }
+
+    /// Enable the read-through storage cache.
+    pub fn with_cache(mut self, cache: StorageCache) -> Self {
+        self.cache = Some(Arc::new(RwLock::new(cache)));
+        self
+    }
+
     pub async fn check_client_runtime_version_and_update(&self) -> Result<(), Error> {
         let timer =   Instant::now();
         let client = self.client.read().await;
@@ -601,6 +1004,13 @@ impl<C: Config, P: Signer<C> + Clone> SubClient<C, P> {
                 self.handle_error(e).await
             },
         };
+        if let (Ok(()), Some(cache)) = (&res, &self.cache) {
+            // drop the cached values after a runtime upgrade
+            let spec_version = self.client.read().await.runtime_version().spec_version;
+            if cache.write().await.check_spec_version(spec_version) {
+                log::info!(target: "subxt", "runtime upgraded, clear storage cache");
+            }
+        }
         if timer.elapsed().as_millis() > self.warn_time {
             log::warn!(target: "subxt", "check_client_runtime_version_and_update exceed warn_time: {} millis", timer.elapsed().as_millis());
         }
@@ -0,0 +1,409 @@
+//! Drive a committee through its lifecycle, from 'create_committee' to 'CommitteeStartWork'.
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use subxt::events::EventDetails;
+use crate::bool::committee::events::{
+    BindAnchor, CommitteeCreateFinished, CommitteeStartWork, CreateCommittee, KeyGenerate, StopCommittee,
+};
+use crate::bool::runtime_types::pallet_channel::types::HandleConnection;
+use crate::bool::runtime_types::pallet_committee::types::{CommitteeState, CryptoType};
+use crate::query::committee::{cid, committees};
+use crate::submit::channel::bind_committees;
+use crate::submit::committee::{active_committee, create_committee};
+use crate::{BoolConfig, BoolSubClient};
+
+/// blocks to wait for the committee created by 'create_committee', it's submitted again after them.
+const SUBMIT_TIMEOUT_BLOCKS: u32 = 20;
+
+/// Parameters of the committee to create and bind.
+#[derive(Clone, Debug)]
+pub struct CommitteePlan {
+    pub t: u16,
+    pub n: u16,
+    pub crypto: CryptoType,
+    pub fork: u8,
+    // chain id and anchor address activated by 'active_committee', a committee has one anchor
+    pub chain_id: u32,
+    pub anchor: Vec<u8>,
+    // channel bound with the committee by 'bind_committees'
+    pub channel_id: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CommitteeStage {
+    // 'create_committee' not submitted
+    #[default]
+    Pending,
+    // 'create_committee' submitted, the created committee is searched from `cid_floor` until block `deadline`,
+    // back to `Pending` if it's not found by then, ie. the process crashed before the submission
+    Submitted { cid_floor: u32, deadline: u32 },
+    // 'CreateCommittee', waiting for the devices to generate the key
+    Creating,
+    // 'KeyGenerate'
+    KeyGenerated,
+    // 'CommitteeCreateFinished', the anchor is activated by 'active_committee'
+    CreateFinished,
+    // the anchor is bound by 'BindAnchor', 'bind_committees' is submitted
+    Binding,
+    // 'CommitteeStartWork'
+    Working,
+    Failed(String),
+}
+
+impl CommitteeStage {
+    pub fn is_final(&self) -> bool {
+        matches!(self, CommitteeStage::Working | CommitteeStage::Failed(_))
+    }
+}
+
+/// Progress of the orchestrator, saved to the progress file on every change to resume after restart.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CommitteeProgress {
+    pub cid: Option<u32>,
+    pub stage: CommitteeStage,
+    pub pubkey: Vec<u8>,
+    // the anchor is verified in storage
+    pub anchor_bound: bool,
+}
+
+impl CommitteeProgress {
+    /// Load the progress saved at `path`, or the default progress if the file doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let bytes = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        // write to temp file then rename, so a crash never leaves a broken progress file
+        let tmp = path.as_ref().with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+}
+
+/// Submit the steps of `CommitteePlan` one by one by `submit::committee` and `submit::channel`,
+/// and verify the committee in storage after every step.
+/// `handle_events` speeds up the progress with the `CommitteeEvent`s sent by `EventWatcher`.
+pub struct CommitteeOrchestrator {
+    plan: CommitteePlan,
+    progress: CommitteeProgress,
+    progress_path: Option<PathBuf>,
+}
+
+impl CommitteeOrchestrator {
+    pub fn new(plan: CommitteePlan) -> Self {
+        CommitteeOrchestrator { plan, progress: CommitteeProgress::default(), progress_path: None }
+    }
+
+    /// Resume the orchestrator with the progress saved at `path`, the progress is saved to it on every change.
+    pub fn open<P: AsRef<Path>>(plan: CommitteePlan, path: P) -> Result<Self, String> {
+        let progress = CommitteeProgress::load(&path)?;
+        Ok(CommitteeOrchestrator { plan, progress, progress_path: Some(path.as_ref().to_path_buf()) })
+    }
+
+    pub fn progress(&self) -> &CommitteeProgress {
+        &self.progress
+    }
+
+    /// Start over from 'create_committee', ie. the 'create_committee' tx was dropped and the committee is never found.
+    pub fn reset(&mut self) -> Result<(), String> {
+        self.progress = CommitteeProgress::default();
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        match &self.progress_path {
+            Some(path) => self.progress.save(path),
+            None => Ok(()),
+        }
+    }
+
+    fn set_stage(&mut self, stage: CommitteeStage) -> Result<(), String> {
+        if self.progress.stage != stage {
+            log::info!(target: "committee_lifecycle", "committee {:?} enter stage: {:?}", self.progress.cid, stage);
+            self.progress.stage = stage;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Handle the committee events of block, ie. the events sent by `EventWatcher`.
+    pub async fn handle_events(
+        &mut self,
+        sub_client: &BoolSubClient,
+        events: &[EventDetails<BoolConfig>],
+    ) -> Result<(), String> {
+        for event in events.iter().filter(|e| e.pallet_name() == "Committee") {
+            if let Some(CreateCommittee(cid, creator)) = event.as_event::<CreateCommittee>().map_err(|e| e.to_string())? {
+                let is_local = creator.0 == sub_client.account_id().await.0;
+                self.on_create_committee(cid, is_local)?;
+            } else if let Some(KeyGenerate(cid, ..)) = event.as_event::<KeyGenerate>().map_err(|e| e.to_string())? {
+                self.on_key_generate(cid)?;
+            } else if let Some(CommitteeCreateFinished(cid, pubkey)) =
+                event.as_event::<CommitteeCreateFinished>().map_err(|e| e.to_string())?
+            {
+                self.on_create_finished(cid, pubkey)?;
+            } else if let Some(BindAnchor(cid, chain_id, anchor)) = event.as_event::<BindAnchor>().map_err(|e| e.to_string())? {
+                if self.progress.cid == Some(cid) && chain_id == self.plan.chain_id && anchor == self.plan.anchor && !self.progress.anchor_bound {
+                    // the event is only a hint, the anchor is verified in storage
+                    if self.anchor_bound(sub_client, cid).await? {
+                        self.progress.anchor_bound = true;
+                        self.save()?;
+                    }
+                }
+            } else if let Some(CommitteeStartWork(cid, _)) = event.as_event::<CommitteeStartWork>().map_err(|e| e.to_string())? {
+                self.on_start_work(cid)?;
+            } else if let Some(StopCommittee(cid)) = event.as_event::<StopCommittee>().map_err(|e| e.to_string())? {
+                self.on_stop(cid)?;
+            }
+        }
+        Ok(())
+    }
+
+    // 'CreateCommittee', `is_local` if it's created by the signer
+    fn on_create_committee(&mut self, cid: u32, is_local: bool) -> Result<(), String> {
+        if let CommitteeStage::Submitted { cid_floor, .. } = self.progress.stage {
+            if cid >= cid_floor && is_local {
+                self.progress.cid = Some(cid);
+                self.set_stage(CommitteeStage::Creating)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_key_generate(&mut self, cid: u32) -> Result<(), String> {
+        if self.progress.cid == Some(cid) && self.progress.stage == CommitteeStage::Creating {
+            self.set_stage(CommitteeStage::KeyGenerated)?;
+        }
+        Ok(())
+    }
+
+    fn on_create_finished(&mut self, cid: u32, pubkey: Vec<u8>) -> Result<(), String> {
+        if self.progress.cid == Some(cid) && matches!(self.progress.stage, CommitteeStage::Creating | CommitteeStage::KeyGenerated) {
+            self.progress.pubkey = pubkey;
+            self.set_stage(CommitteeStage::CreateFinished)?;
+        }
+        Ok(())
+    }
+
+    fn on_start_work(&mut self, cid: u32) -> Result<(), String> {
+        if self.progress.cid == Some(cid) {
+            self.set_stage(CommitteeStage::Working)?;
+        }
+        Ok(())
+    }
+
+    fn on_stop(&mut self, cid: u32) -> Result<(), String> {
+        if self.progress.cid == Some(cid) {
+            self.set_stage(CommitteeStage::Failed("committee stopped".to_string()))?;
+        }
+        Ok(())
+    }
+
+    // back to `Pending` if the submitted committee is still not found after its deadline at `block`,
+    // so 'create_committee' is submitted again by the next `advance`
+    fn expire_submission(&mut self, block: u32) -> Result<(), String> {
+        if let CommitteeStage::Submitted { deadline, .. } = self.progress.stage {
+            if block > deadline {
+                log::warn!(target: "committee_lifecycle", "committee not created before block {deadline}, submit again");
+                self.set_stage(CommitteeStage::Pending)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify the progress with storage and submit the next step if it's ready, return the stage after it.
+    pub async fn advance(&mut self, sub_client: &BoolSubClient) -> Result<CommitteeStage, String> {
+        match self.progress.stage.clone() {
+            CommitteeStage::Pending => {
+                let cid_floor = cid(sub_client, None).await.map_err(|e| e.to_string())?;
+                let deadline = latest_block_number(sub_client).await? + SUBMIT_TIMEOUT_BLOCKS;
+                // save before submission, so the committee won't be created twice after restart
+                self.set_stage(CommitteeStage::Submitted { cid_floor, deadline })?;
+                if let Err(e) = create_committee(
+                    sub_client,
+                    self.plan.t,
+                    self.plan.n,
+                    self.plan.crypto.clone(),
+                    self.plan.fork,
+                    None,
+                )
+                .await
+                {
+                    self.set_stage(CommitteeStage::Pending)?;
+                    return Err(e);
+                }
+                self.find_created(sub_client, cid_floor).await?;
+            }
+            CommitteeStage::Submitted { cid_floor, .. } => {
+                self.find_created(sub_client, cid_floor).await?;
+                let block = latest_block_number(sub_client).await?;
+                self.expire_submission(block)?;
+            }
+            CommitteeStage::Working | CommitteeStage::Failed(_) => {}
+            _ => self.sync_committee(sub_client).await?,
+        }
+        Ok(self.progress.stage.clone())
+    }
+
+    /// Advance until the committee is working or failed, check it every `poll_interval`.
+    pub async fn run_until_final(
+        &mut self,
+        sub_client: &BoolSubClient,
+        poll_interval: Duration,
+    ) -> Result<CommitteeProgress, String> {
+        while !self.advance(sub_client).await?.is_final() {
+            tokio::time::sleep(poll_interval).await;
+        }
+        Ok(self.progress.clone())
+    }
+
+    // find the committee created by the signer from `cid_floor`
+    async fn find_created(&mut self, sub_client: &BoolSubClient, cid_floor: u32) -> Result<(), String> {
+        let account = sub_client.account_id().await;
+        let latest = cid(sub_client, None).await.map_err(|e| e.to_string())?;
+        for id in cid_floor..=latest {
+            let committee = committees(sub_client, id, None).await.map_err(|e| e.to_string())?;
+            if committee.map_or(false, |c| c.creator.0 == account.0) {
+                self.progress.cid = Some(id);
+                self.set_stage(CommitteeStage::Creating)?;
+                return self.sync_committee(sub_client).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn sync_committee(&mut self, sub_client: &BoolSubClient) -> Result<(), String> {
+        let Some(cid) = self.progress.cid else {
+            return Ok(());
+        };
+        let Some(committee) = committees(sub_client, cid, None).await.map_err(|e| e.to_string())? else {
+            return self.set_stage(CommitteeStage::Failed(format!("committee {cid} not found")));
+        };
+        match committee.state {
+            CommitteeState::Creating | CommitteeState::Initializing => Ok(()),
+            CommitteeState::Stop => self.set_stage(CommitteeStage::Failed("committee stopped".to_string())),
+            CommitteeState::Working => {
+                self.progress.pubkey = committee.pubkey;
+                self.set_stage(CommitteeStage::Working)
+            }
+            CommitteeState::CreateFinished => {
+                self.progress.pubkey = committee.pubkey;
+                if matches!(self.progress.stage, CommitteeStage::Creating | CommitteeStage::KeyGenerated) {
+                    self.set_stage(CommitteeStage::CreateFinished)?;
+                }
+                self.bind(sub_client, cid).await
+            }
+        }
+    }
+
+    // the committee is anchored at the planned anchor in storage
+    async fn anchor_bound(&self, sub_client: &BoolSubClient, cid: u32) -> Result<bool, String> {
+        let committee = committees(sub_client, cid, None).await.map_err(|e| e.to_string())?;
+        Ok(committee.map_or(false, |c| c.chain_id == self.plan.chain_id && c.anchor == self.plan.anchor))
+    }
+
+    // activate the anchor if it's not bound yet, then bind the committee to the channel
+    async fn bind(&mut self, sub_client: &BoolSubClient, cid: u32) -> Result<(), String> {
+        let (chain_id, anchor) = (self.plan.chain_id, self.plan.anchor.clone());
+        if !self.progress.anchor_bound {
+            // skip the anchor activated before restart
+            if !self.anchor_bound(sub_client, cid).await? {
+                active_committee(sub_client, cid, chain_id, anchor.clone(), None).await?;
+                if !self.anchor_bound(sub_client, cid).await? {
+                    return Err(format!("anchor of chain {chain_id} not bound to committee {cid}"));
+                }
+            }
+            self.progress.anchor_bound = true;
+            self.save()?;
+        }
+        if self.progress.stage == CommitteeStage::CreateFinished {
+            let connections = vec![HandleConnection::CidWithAnchor(cid, chain_id, anchor)];
+            bind_committees(sub_client, self.plan.channel_id, connections, None).await?;
+            // the committee state becomes 'Working' with 'CommitteeStartWork'
+            self.set_stage(CommitteeStage::Binding)?;
+        }
+        Ok(())
+    }
+}
+
+async fn latest_block_number(sub_client: &BoolSubClient) -> Result<u32, String> {
+    let block = sub_client.client.read().await.blocks().at_latest().await.map_err(|e| e.to_string())?;
+    Ok(block.number())
+}
+
+#[test]
+fn test_progress_save_load() {
+    let path = std::env::temp_dir().join(format!("committee_progress_{}.json", std::process::id()));
+    assert_eq!(CommitteeProgress::load(&path).unwrap().stage, CommitteeStage::Pending);
+    let progress = CommitteeProgress {
+        cid: Some(3),
+        stage: CommitteeStage::Submitted { cid_floor: 2, deadline: 30 },
+        pubkey: vec![1, 2],
+        anchor_bound: true,
+    };
+    progress.save(&path).unwrap();
+    assert!(!path.with_extension("tmp").exists());
+    let loaded = CommitteeProgress::load(&path).unwrap();
+    assert_eq!((loaded.cid, loaded.stage, loaded.anchor_bound), (Some(3), CommitteeStage::Submitted { cid_floor: 2, deadline: 30 }, true));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_stage_transitions() {
+    let plan = CommitteePlan {
+        t: 2,
+        n: 3,
+        crypto: CryptoType::Ecdsa,
+        fork: 0,
+        chain_id: 1,
+        anchor: vec![1; 20],
+        channel_id: 1,
+    };
+    let mut orchestrator = CommitteeOrchestrator::new(plan);
+    let stage = |o: &CommitteeOrchestrator| o.progress().stage.clone();
+
+    // events before submission and of other committees are ignored
+    orchestrator.on_create_committee(5, true).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Pending);
+    orchestrator.set_stage(CommitteeStage::Submitted { cid_floor: 5, deadline: 120 }).unwrap();
+    orchestrator.on_create_committee(4, true).unwrap();
+    orchestrator.on_create_committee(5, false).unwrap();
+    orchestrator.on_key_generate(5).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Submitted { cid_floor: 5, deadline: 120 });
+
+    // the submission expires after the deadline
+    orchestrator.expire_submission(120).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Submitted { cid_floor: 5, deadline: 120 });
+    orchestrator.expire_submission(121).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Pending);
+
+    orchestrator.set_stage(CommitteeStage::Submitted { cid_floor: 5, deadline: 140 }).unwrap();
+    orchestrator.on_create_committee(6, true).unwrap();
+    assert_eq!((orchestrator.progress().cid, stage(&orchestrator)), (Some(6), CommitteeStage::Creating));
+    orchestrator.expire_submission(200).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Creating);
+
+    orchestrator.on_key_generate(5).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Creating);
+    orchestrator.on_key_generate(6).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::KeyGenerated);
+    orchestrator.on_create_finished(6, vec![2; 33]).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::CreateFinished);
+    assert_eq!(orchestrator.progress().pubkey, vec![2; 33]);
+    // a late 'KeyGenerate' doesn't move the stage back
+    orchestrator.on_key_generate(6).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::CreateFinished);
+
+    orchestrator.on_start_work(5).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::CreateFinished);
+    orchestrator.on_start_work(6).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Working);
+    orchestrator.on_stop(6).unwrap();
+    assert_eq!(stage(&orchestrator), CommitteeStage::Failed("committee stopped".to_string()));
+}
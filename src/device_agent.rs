@@ -0,0 +1,228 @@
+//! Device lifecycle in the mining pallet, with the transitions checked against chain storage before submission.
+use sp_core::H256 as Hash;
+use subxt::events::EventDetails;
+use crate::bool::mining::events::{DeviceExitService, DeviceJoinService, DeviceRemoved, DeviceTryExitService};
+use crate::bool::runtime_types::fp_account::AccountId20;
+use crate::bool::runtime_types::pallet_facility::pallet::DIdentity;
+use crate::bool::runtime_types::pallet_mining::types::{DeviceState, MonitorState, MonitorType, OnChainPayload};
+use crate::query::mining::{device_info, device_monitor_state, working_devices};
+use crate::query::rpc::device_info_rpc;
+use crate::submit::mining::{exit_service, im_online, join_service, register_device_with_ident, report_standby};
+use crate::submit::rpc::register_device_rpc;
+use crate::{BoolConfig, BoolSubClient};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum DeviceLifecycle {
+    // not in 'Mining.Devices', or removed by 'DeviceRemoved'
+    #[default]
+    Unregistered,
+    // 'DeviceState::Standby', registered or exited by 'DeviceExitService'
+    Standby,
+    // 'DeviceState::Working' after 'DeviceJoinService'
+    Working {
+        // the device is in 'WorkingDevices' of the current session
+        in_session: bool,
+        // the heartbeat of the current session is received
+        heartbeat_received: bool,
+    },
+    // 'DeviceState::Exiting' after 'DeviceTryExitService', the device exits at `exiting_block`
+    Exiting { exiting_block: Option<u32> },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeviceAction {
+    Register,
+    ReportStandby,
+    JoinService,
+    Heartbeat,
+    ExitService,
+}
+
+impl DeviceLifecycle {
+    /// Check if `action` is allowed in the lifecycle, return the error before it's rejected by the chain.
+    pub fn check(&self, action: DeviceAction) -> Result<(), String> {
+        let allowed = match action {
+            DeviceAction::Register => matches!(self, DeviceLifecycle::Unregistered),
+            DeviceAction::ReportStandby | DeviceAction::JoinService => matches!(self, DeviceLifecycle::Standby),
+            DeviceAction::Heartbeat => {
+                matches!(self, DeviceLifecycle::Working { in_session: true, heartbeat_received: false })
+            }
+            DeviceAction::ExitService => matches!(self, DeviceLifecycle::Working { .. }),
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("invalid device transition: {action:?} in {self:?}"))
+        }
+    }
+}
+
+/// Client of one device, the lifecycle is read from 'device_info', 'device_monitor_state' and 'working_devices'
+/// by `refresh`, which is called after every submitted transition.
+#[derive(Clone)]
+pub struct DeviceAgent {
+    client: BoolSubClient,
+    pub device_id: Vec<u8>,
+    pub lifecycle: DeviceLifecycle,
+    pub version: u16,
+    pub monitor_state: Option<MonitorState>,
+    // registered by 'rpc::register_device_rpc'
+    pub rpc_registered: bool,
+    pub session: u32,
+}
+
+impl DeviceAgent {
+    pub fn new(client: BoolSubClient, device_id: Vec<u8>) -> Self {
+        DeviceAgent {
+            client,
+            device_id,
+            lifecycle: DeviceLifecycle::default(),
+            version: 0,
+            monitor_state: None,
+            rpc_registered: false,
+            session: 0,
+        }
+    }
+
+    /// Read the lifecycle of device from storage at `at_block`.
+    pub async fn refresh(&mut self, at_block: Option<Hash>) -> Result<&DeviceLifecycle, subxt::Error> {
+        let block_hash = self.client.block_hash_or_latest(at_block).await?;
+        let info = device_info(&self.client, self.device_id.clone(), Some(block_hash)).await?;
+        self.monitor_state = device_monitor_state(&self.client, self.device_id.clone(), Some(block_hash)).await?;
+        self.rpc_registered = device_info_rpc(&self.client, self.device_id.clone(), Some(block_hash)).await?.is_some();
+        let (devices, session) = working_devices(&self.client, None, Some(block_hash)).await?.unwrap_or_default();
+        self.session = session;
+        self.lifecycle = match info {
+            None => DeviceLifecycle::Unregistered,
+            Some(info) => {
+                self.version = info.version;
+                match info.state {
+                    DeviceState::Standby => DeviceLifecycle::Standby,
+                    DeviceState::Working => {
+                        let device = devices.iter().find(|(did, _)| did.pk == self.device_id);
+                        DeviceLifecycle::Working {
+                            in_session: device.is_some(),
+                            heartbeat_received: device.map_or(false, |(_, received)| *received),
+                        }
+                    }
+                    DeviceState::Exiting => DeviceLifecycle::Exiting { exiting_block: info.exiting_block },
+                }
+            }
+        };
+        Ok(&self.lifecycle)
+    }
+
+    /// Update the lifecycle by the mining events of device, ie. the events sent by `EventWatcher`.
+    pub fn handle_events(&mut self, events: &[EventDetails<BoolConfig>]) -> Result<(), subxt::Error> {
+        for event in events.iter().filter(|e| e.pallet_name() == "Mining") {
+            if let Some(DeviceJoinService(id)) = event.as_event::<DeviceJoinService>()? {
+                if id == self.device_id {
+                    self.lifecycle = DeviceLifecycle::Working { in_session: false, heartbeat_received: false };
+                }
+            } else if let Some(DeviceTryExitService(id)) = event.as_event::<DeviceTryExitService>()? {
+                if id == self.device_id {
+                    self.lifecycle = DeviceLifecycle::Exiting { exiting_block: None };
+                }
+            } else if let Some(DeviceExitService(id)) = event.as_event::<DeviceExitService>()? {
+                if id == self.device_id {
+                    self.lifecycle = DeviceLifecycle::Standby;
+                }
+            } else if let Some(DeviceRemoved(id)) = event.as_event::<DeviceRemoved>()? {
+                if id == self.device_id {
+                    self.lifecycle = DeviceLifecycle::Unregistered;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // refresh and check `action` before submission
+    async fn ensure(&mut self, action: DeviceAction) -> Result<(), String> {
+        self.refresh(None).await.map_err(|e| e.to_string())?;
+        self.lifecycle.check(action)
+    }
+
+    async fn after_submit(&mut self, res: Result<Hash, String>) -> Result<Hash, String> {
+        let hash = res?;
+        if let Err(e) = self.refresh(None).await {
+            log::warn!(target: "device_agent", "refresh device {} failed for: {e:?}", hex::encode(&self.device_id));
+        }
+        Ok(hash)
+    }
+
+    pub async fn register(
+        &mut self,
+        owner: AccountId20,
+        report: Vec<u8>,
+        version: u16,
+        identity: Vec<u8>,
+        monitor_type: MonitorType,
+        signature: Vec<u8>,
+    ) -> Result<Hash, String> {
+        self.ensure(DeviceAction::Register).await?;
+        let res = register_device_with_ident(&self.client, owner, report, version, identity, monitor_type, signature).await;
+        self.after_submit(res).await
+    }
+
+    /// Register the device by the rpc pallet, it's refused if the device is registered by either pallet.
+    pub async fn register_rpc(
+        &mut self,
+        owner: AccountId20,
+        report: Vec<u8>,
+        version: u16,
+        signature: Vec<u8>,
+    ) -> Result<Hash, String> {
+        self.ensure(DeviceAction::Register).await?;
+        if self.rpc_registered {
+            return Err("invalid device transition: device is registered by rpc".to_string());
+        }
+        let res = register_device_rpc(&self.client, owner, report, version, signature, self.device_id.clone()).await;
+        self.after_submit(res).await
+    }
+
+    pub async fn report_standby(&mut self, version: u16, enclave_hash: Vec<u8>, signature: Vec<u8>) -> Result<Hash, String> {
+        self.ensure(DeviceAction::ReportStandby).await?;
+        let res = report_standby(&self.client, self.device_id.clone(), version, enclave_hash, signature).await;
+        self.after_submit(res).await
+    }
+
+    pub async fn join_service(&mut self) -> Result<Hash, String> {
+        self.ensure(DeviceAction::JoinService).await?;
+        let res = join_service(&self.client, self.device_id.clone(), None).await;
+        self.after_submit(res).await
+    }
+
+    /// Send the heartbeat of the current session by 'im_online'.
+    pub async fn heartbeat(&mut self, signature: Vec<u8>, proof: Vec<u8>, enclave: Vec<u8>) -> Result<Hash, String> {
+        self.ensure(DeviceAction::Heartbeat).await?;
+        let payload = OnChainPayload {
+            did: DIdentity { version: self.version, pk: self.device_id.clone() },
+            proof,
+            session: self.session,
+            signature,
+            enclave,
+        };
+        let res = im_online(&self.client, payload).await;
+        self.after_submit(res).await
+    }
+
+    /// Try to exit service, the device is 'Exiting' until 'DeviceExitService'.
+    pub async fn exit_service(&mut self) -> Result<Hash, String> {
+        self.ensure(DeviceAction::ExitService).await?;
+        let res = exit_service(&self.client, self.device_id.clone(), None).await;
+        self.after_submit(res).await
+    }
+}
+
+#[test]
+fn test_device_lifecycle_check() {
+    let working = DeviceLifecycle::Working { in_session: true, heartbeat_received: false };
+    assert!(DeviceLifecycle::Unregistered.check(DeviceAction::Register).is_ok());
+    assert!(DeviceLifecycle::Unregistered.check(DeviceAction::JoinService).is_err());
+    assert!(DeviceLifecycle::Standby.check(DeviceAction::JoinService).is_ok());
+    assert!(DeviceLifecycle::Standby.check(DeviceAction::Register).is_err());
+    assert!(working.check(DeviceAction::Heartbeat).is_ok());
+    assert!(working.check(DeviceAction::ExitService).is_ok());
+    assert!(DeviceLifecycle::Working { in_session: true, heartbeat_received: true }.check(DeviceAction::Heartbeat).is_err());
+    assert!(DeviceLifecycle::Exiting { exiting_block: Some(10) }.check(DeviceAction::ExitService).is_err());
+}
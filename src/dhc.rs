@@ -0,0 +1,312 @@
+//! Participation client of the committee health (DHC) pallet for court members and challenged identities.
+use sp_core::H256 as Hash;
+use subxt::events::EventDetails;
+use crate::bool::committee_health::events::{Challenges, ConfirmDHCState, HealthReport, PunishEvilDevice};
+use crate::bool::runtime_types::pallet_committee_health::pallet::{ConsensusStage, DHCState};
+use crate::query::committee_health::{
+    consensus_confirms, consensus_state, court_members, identity_challenge, state_votes,
+};
+use crate::submit::committee_health::{
+    report_health, report_health_call_bytes, report_health_signed, report_state_vote, report_state_vote_call_bytes,
+    report_state_vote_signed,
+};
+use crate::{BoolConfig, BoolSubClient};
+
+/// Signer of the local identity, ie. the key in enclave.
+pub trait DhcSigner: Send + Sync {
+    /// Sign the `challenge` of `identity` for 'report_health'.
+    fn sign_challenge(&self, identity: &[u8], challenge: &[u8]) -> Result<Vec<u8>, String>;
+    /// Sign the consensus `state` for 'report_state_vote'.
+    fn sign_state(&self, state: &DHCState) -> Result<Vec<u8>, String>;
+}
+
+/// Route of the calls signed by `DhcSigner`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum DhcRoute {
+    // submit the unsigned call to Bool chain
+    #[default]
+    Submit,
+    // submit the call signed by the client signer, which pays the fee
+    Signed,
+    // return the encoded unsigned call, ie. for the relayer to submit
+    CallBytes,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DhcDuty {
+    // the local identity is challenged by 'Challenges'
+    ReportHealth { challenge: Vec<u8> },
+    // the local device is a court member and the consensus state is not confirmed
+    VoteState { state: DHCState },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DhcSubmission {
+    Submitted(Hash),
+    CallBytes(Vec<u8>),
+}
+
+/// Duty state of the local identity, read from storage by `DhcClient::refresh` and updated by the events.
+/// Challenges, reports, court members and punishments are matched to both the identity and the device id.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DhcStatus {
+    pub identity: Vec<u8>,
+    pub device_id: Vec<u8>,
+    pub is_court_member: bool,
+    // active consensus state and whether it's confirmed
+    pub state: Option<DHCState>,
+    pub confirmed: bool,
+    pub pending_challenge: Option<Vec<u8>>,
+    // the local identity is punished by 'PunishEvilDevice'
+    pub punished: bool,
+    // the state voted by the local device
+    pub voted: Option<DHCState>,
+}
+
+impl DhcStatus {
+    pub fn new(identity: Vec<u8>, device_id: Vec<u8>) -> Self {
+        DhcStatus { identity, device_id, ..Default::default() }
+    }
+
+    pub fn is_local(&self, id: &[u8]) -> bool {
+        id == self.identity.as_slice() || id == self.device_id.as_slice()
+    }
+
+    /// Seed `voted` by the vote of the device in 'StateVotes' when the active `state` is read from storage.
+    /// A state voted by the client for an earlier epoch is dropped, and the vote in storage is not for the
+    /// new state then, otherwise the vote in storage is for the active state until it's confirmed.
+    pub fn seed_vote(&mut self, stored_vote: bool) {
+        let Some(state) = &self.state else {
+            self.voted = None;
+            return;
+        };
+        self.voted = match self.voted.take() {
+            Some(voted) if voted.epoch == state.epoch => Some(voted),
+            Some(_) => None,
+            None => (stored_vote && !self.confirmed).then(|| state.clone()),
+        };
+    }
+
+    /// 'Challenges' of `idents`.
+    pub fn on_challenges(&mut self, idents: &[Vec<u8>], challenge: Vec<u8>) {
+        if idents.iter().any(|ident| self.is_local(ident)) {
+            self.pending_challenge = Some(challenge);
+        }
+    }
+
+    /// 'HealthReport' of `ident`.
+    pub fn on_health_report(&mut self, ident: &[u8]) {
+        if self.is_local(ident) {
+            self.pending_challenge = None;
+        }
+    }
+
+    /// 'ConfirmDHCState' of `state`.
+    pub fn on_confirm(&mut self, state: DHCState) {
+        self.confirmed = true;
+        self.state = Some(state);
+    }
+
+    /// 'PunishEvilDevice' of `devices`.
+    pub fn on_punish(&mut self, devices: &[Vec<u8>]) {
+        if devices.iter().any(|device| self.is_local(device)) {
+            log::error!(target: "dhc", "local identity 0x{} is punished as evil device", hex::encode(&self.identity));
+            self.punished = true;
+        }
+    }
+
+    /// Duties of the local identity, a state is voted at most once.
+    pub fn duties(&self) -> Vec<DhcDuty> {
+        let mut duties = Vec::new();
+        if let Some(challenge) = &self.pending_challenge {
+            duties.push(DhcDuty::ReportHealth { challenge: challenge.clone() });
+        }
+        if let (true, false, Some(state)) = (self.is_court_member, self.confirmed, &self.state) {
+            if self.voted.as_ref() != Some(state) {
+                duties.push(DhcDuty::VoteState { state: state.clone() });
+            }
+        }
+        duties
+    }
+}
+
+/// Follow the 'Challenges', 'HealthReport', 'ConfirmDHCState' and 'PunishEvilDevice' events and the storage
+/// of committee health, to decide the duties of the local identity and perform them by `DhcSigner`.
+pub struct DhcClient<S> {
+    client: BoolSubClient,
+    signer: S,
+    pub route: DhcRoute,
+    pub status: DhcStatus,
+    // re-read the storage before the next `perform`, ie. the court members may change after 'ConfirmDHCState'
+    stale: bool,
+}
+
+impl<S: DhcSigner> DhcClient<S> {
+    pub fn new(client: BoolSubClient, identity: Vec<u8>, device_id: Vec<u8>, signer: S) -> Self {
+        DhcClient {
+            client,
+            signer,
+            route: DhcRoute::default(),
+            status: DhcStatus::new(identity, device_id),
+            stale: true,
+        }
+    }
+
+    pub fn with_route(mut self, route: DhcRoute) -> Self {
+        self.route = route;
+        self
+    }
+
+    /// Active consensus stage of committee health.
+    pub fn stage(&self) -> Option<&ConsensusStage> {
+        self.status.state.as_ref().map(|state| &state.stage)
+    }
+
+    /// Read the court members, consensus state and challenge of the local identity at `at_block`.
+    pub async fn refresh(&mut self, at_block: Option<Hash>) -> Result<(), subxt::Error> {
+        let block_hash = self.client.block_hash_or_latest(at_block).await?;
+        let status = &mut self.status;
+        status.is_court_member = court_members(&self.client, Some(block_hash))
+            .await?
+            .unwrap_or_default()
+            .iter()
+            .any(|member| status.is_local(member));
+        status.state = consensus_state(&self.client, Some(block_hash)).await?;
+        status.confirmed = match &status.state {
+            Some(state) => consensus_confirms(&self.client, state.epoch, state.stage.clone(), Some(block_hash))
+                .await?
+                .map_or(false, |confirm| &confirm.state == state),
+            None => false,
+        };
+        let vote = state_votes(&self.client, status.device_id.clone(), Some(block_hash)).await?;
+        status.seed_vote(!vote.is_empty());
+        let (_, challenge) = identity_challenge(&self.client, status.identity.clone(), Some(block_hash)).await?;
+        status.pending_challenge = (!challenge.is_empty()).then_some(challenge);
+        self.stale = false;
+        Ok(())
+    }
+
+    /// Update the state by the committee health events, ie. the events sent by `EventWatcher`.
+    pub fn handle_events(&mut self, events: &[EventDetails<BoolConfig>]) -> Result<(), subxt::Error> {
+        for event in events.iter().filter(|e| e.pallet_name() == "CommitteeHealth") {
+            if let Some(Challenges(idents, challenge)) = event.as_event::<Challenges>()? {
+                self.status.on_challenges(&idents, challenge);
+            } else if let Some(HealthReport(ident)) = event.as_event::<HealthReport>()? {
+                self.status.on_health_report(&ident);
+            } else if let Some(ConfirmDHCState(state)) = event.as_event::<ConfirmDHCState>()? {
+                self.status.on_confirm(state);
+                // the next consensus state and court members are read before the next duties
+                self.stale = true;
+            } else if let Some(PunishEvilDevice(devices)) = event.as_event::<PunishEvilDevice>()? {
+                self.status.on_punish(&devices);
+            }
+        }
+        Ok(())
+    }
+
+    /// Duties of the local identity, a state is voted at most once by the client.
+    pub fn duties(&self) -> Vec<DhcDuty> {
+        self.status.duties()
+    }
+
+    /// Sign and submit the duties by `route`, return the result of every duty, a failed duty is retried by the next call.
+    /// The storage is re-read first if it's stale.
+    pub async fn perform(&mut self) -> Result<Vec<(DhcDuty, Result<DhcSubmission, String>)>, String> {
+        if self.stale {
+            self.refresh(None).await.map_err(|e| e.to_string())?;
+        }
+        let mut res = Vec::new();
+        for duty in self.duties() {
+            let submission = self.perform_duty(&duty).await;
+            if submission.is_ok() {
+                match &duty {
+                    DhcDuty::ReportHealth { .. } => self.status.pending_challenge = None,
+                    DhcDuty::VoteState { state } => self.status.voted = Some(state.clone()),
+                }
+            }
+            res.push((duty, submission));
+        }
+        Ok(res)
+    }
+
+    async fn perform_duty(&self, duty: &DhcDuty) -> Result<DhcSubmission, String> {
+        match duty {
+            DhcDuty::ReportHealth { challenge } => {
+                let sig = self.signer.sign_challenge(&self.status.identity, challenge)?;
+                let ident = self.status.identity.clone();
+                match self.route {
+                    DhcRoute::Submit => report_health(&self.client, ident, sig).await.map(DhcSubmission::Submitted),
+                    DhcRoute::Signed => {
+                        report_health_signed(&self.client, ident, sig, None).await.map(DhcSubmission::Submitted)
+                    }
+                    DhcRoute::CallBytes => {
+                        report_health_call_bytes(&self.client, ident, sig).await.map(DhcSubmission::CallBytes)
+                    }
+                }
+            }
+            DhcDuty::VoteState { state } => {
+                let sig = self.signer.sign_state(state)?;
+                let device_id = self.status.device_id.clone();
+                match self.route {
+                    DhcRoute::Submit => report_state_vote(&self.client, device_id, sig).await.map(DhcSubmission::Submitted),
+                    DhcRoute::Signed => {
+                        report_state_vote_signed(&self.client, device_id, sig, None).await.map(DhcSubmission::Submitted)
+                    }
+                    DhcRoute::CallBytes => {
+                        report_state_vote_call_bytes(&self.client, device_id, sig).await.map(DhcSubmission::CallBytes)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_duties() {
+    let state = |epoch, stage| DHCState { epoch, stage, hash: vec![epoch as u8] };
+    let mut status = DhcStatus::new(b"identity".to_vec(), b"device".to_vec());
+    assert!(status.duties().is_empty());
+
+    // challenged by the identity or the device id
+    status.on_challenges(&[b"other".to_vec()], vec![1]);
+    assert!(status.duties().is_empty());
+    status.on_challenges(&[b"other".to_vec(), b"device".to_vec()], vec![2]);
+    assert_eq!(status.duties(), vec![DhcDuty::ReportHealth { challenge: vec![2] }]);
+    status.on_health_report(b"identity");
+    assert!(status.duties().is_empty());
+
+    // a court member votes the unconfirmed state once
+    status.state = Some(state(3, ConsensusStage::Snapshot));
+    assert!(status.duties().is_empty());
+    status.is_court_member = true;
+    assert_eq!(status.duties(), vec![DhcDuty::VoteState { state: state(3, ConsensusStage::Snapshot) }]);
+    status.voted = Some(state(3, ConsensusStage::Snapshot));
+    assert!(status.duties().is_empty());
+
+    // the next stage of the epoch is voted again
+    status.state = Some(state(3, ConsensusStage::CandidatePool));
+    assert_eq!(status.duties(), vec![DhcDuty::VoteState { state: state(3, ConsensusStage::CandidatePool) }]);
+
+    status.on_confirm(state(3, ConsensusStage::CandidatePool));
+    assert!(status.duties().is_empty());
+}
+
+#[test]
+fn test_seed_vote() {
+    let state = |epoch| DHCState { epoch, stage: ConsensusStage::Snapshot, hash: vec![] };
+    let mut status = DhcStatus::new(b"identity".to_vec(), b"device".to_vec());
+    status.state = Some(state(3));
+    status.seed_vote(true);
+    assert_eq!(status.voted, Some(state(3)));
+
+    // the vote in storage is not for the state of a new epoch
+    status.state = Some(state(4));
+    status.seed_vote(true);
+    assert_eq!(status.voted, None);
+    status.seed_vote(false);
+    assert_eq!(status.voted, None);
+
+    status.confirmed = true;
+    status.seed_vote(true);
+    assert_eq!(status.voted, None);
+}
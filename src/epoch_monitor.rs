@@ -0,0 +1,316 @@
+//! Monitor the epoch transitions of committees, and alert the committees at risk of failing the handover.
+use std::collections::HashMap;
+use sp_core::H256 as Hash;
+use subxt::events::EventDetails;
+use crate::bool::committee::calls::ReportChange;
+use crate::bool::committee::events::{ApplyEpochChange, KeyHandover};
+use crate::bool::runtime_types::pallet_committee::types::{CommitteeState, GlobalConfig};
+use crate::query::committee::{
+    committee_members, committees, committees_iter, epoch_change_failures, epoch_config, fork_ids, global_epoch,
+    next_epoch_config,
+};
+use crate::{BoolConfig, BoolSubClient};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForkTransition {
+    pub fork_id: u8,
+    pub members: Vec<Vec<u8>>,
+    // pks of the members which submitted 'report_change' for the epoch of committee since the last applied epoch change
+    pub reporters: Vec<Vec<u8>>,
+    pub failures: u8,
+}
+
+/// Epoch transition status of a committee read at one block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpochTransition {
+    pub cid: u32,
+    pub block_number: u32,
+    pub global_epoch: u64,
+    // epoch of the committee
+    pub epoch: u32,
+    // signing threshold of the committee
+    pub threshold: u16,
+    pub forks: Vec<ForkTransition>,
+    // failures limit of 'EpochConfig', the committee fails when it's reached
+    pub failure_limit: u8,
+    // block of the change period before the next epoch boundary, the handover should be reported before it
+    pub deadline_block: u32,
+    pub next_config: Option<GlobalConfig<u32>>,
+    // (block number, epoch) of the last 'ApplyEpochChange' of the committee
+    pub last_applied: Option<(u32, u32)>,
+    // block number of the last 'KeyHandover' of the committee
+    pub last_handover: Option<u32>,
+}
+
+impl EpochTransition {
+    pub fn blocks_to_deadline(&self) -> u32 {
+        self.deadline_block.saturating_sub(self.block_number)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EpochAlert {
+    // the failures of fork are close to the limit
+    FailuresNearLimit { cid: u32, fork_id: u8, failures: u8, limit: u8 },
+    // the deadline is close but the reports of fork are less than the threshold
+    ReportsMissing { cid: u32, fork_id: u8, reported: u16, threshold: u16, blocks_left: u32 },
+    // the fork has no members for the epoch
+    NoMembers { cid: u32, fork_id: u8 },
+}
+
+#[derive(Clone, Debug)]
+pub struct AlertConfig {
+    // alert when the failures are no more than `failure_margin` below the limit
+    pub failure_margin: u8,
+    // alert missing reports in the last `deadline_margin` blocks before the deadline
+    pub deadline_margin: u32,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        AlertConfig { failure_margin: 1, deadline_margin: 20 }
+    }
+}
+
+/// Block of the first epoch boundary after `block_number`, the boundaries are `from + k * epoch_blocks` of `config`.
+pub fn next_epoch_boundary(config: &GlobalConfig<u32>, block_number: u32) -> u32 {
+    if config.epoch_blocks == 0 || block_number < config.from {
+        return config.from;
+    }
+    let passed = (block_number - config.from) / config.epoch_blocks + 1;
+    config.from.saturating_add(passed.saturating_mul(config.epoch_blocks))
+}
+
+/// Block of the change period before the next epoch boundary after `block_number`, by `change_blocks` of `config`.
+pub fn report_deadline(config: &GlobalConfig<u32>, block_number: u32) -> u32 {
+    next_epoch_boundary(config, block_number).saturating_sub(config.change_blocks)
+}
+
+/// Track the 'report_change' extrinsics and epoch change events of blocks,
+/// and compute the `EpochTransition` of committees with storage.
+/// Only 'report_change' submitted by the substrate route is tracked, the evm route is an ethereum transaction.
+#[derive(Clone, Debug, Default)]
+pub struct EpochMonitor {
+    pub alert_config: AlertConfig,
+    // (cid, epoch, fork_id) -> pks reported for the current epoch of committee since the last applied epoch change
+    reports: HashMap<(u32, u32, u8), Vec<Vec<u8>>>,
+    // cid -> (block number, epoch)
+    applied: HashMap<u32, (u32, u32)>,
+    // cid -> block number
+    handovers: HashMap<u32, u32>,
+}
+
+impl EpochMonitor {
+    pub fn new(alert_config: AlertConfig) -> Self {
+        EpochMonitor { alert_config, ..Default::default() }
+    }
+
+    /// Handle the block with its committee events, ie. the events sent by `EventWatcher`.
+    pub async fn handle_block(
+        &mut self,
+        sub_client: &BoolSubClient,
+        block: u32,
+        block_hash: Hash,
+        events: &[EventDetails<BoolConfig>],
+    ) -> Result<(), subxt::Error> {
+        let body = sub_client.client.read().await.blocks().at(block_hash).await?.body().await?;
+        // cid -> current epoch of committee at the block
+        let mut epochs = HashMap::new();
+        for extrinsic in body.extrinsics().iter() {
+            if let Some(report) = extrinsic?.as_extrinsic::<ReportChange>()? {
+                let epoch = match epochs.get(&report.cid) {
+                    Some(epoch) => *epoch,
+                    None => {
+                        let epoch = committees(sub_client, report.cid, Some(block_hash)).await?.map(|c| c.epoch);
+                        *epochs.entry(report.cid).or_insert(epoch)
+                    }
+                };
+                // reports of the other epochs never count for the handover
+                if epoch == Some(report.epoch) {
+                    self.record_report(report.cid, report.epoch, report.fork_id, report.pk);
+                }
+            }
+        }
+        for event in events.iter().filter(|e| e.pallet_name() == "Committee") {
+            if let Some(ApplyEpochChange(changes)) = event.as_event::<ApplyEpochChange>()? {
+                for (cid, epoch, _) in changes {
+                    self.applied.insert(cid, (block, epoch));
+                    self.reports.retain(|(c, ..), _| *c != cid);
+                }
+            } else if let Some(KeyHandover(cid, ..)) = event.as_event::<KeyHandover>()? {
+                self.handovers.insert(cid, block);
+            }
+        }
+        Ok(())
+    }
+
+    fn record_report(&mut self, cid: u32, epoch: u32, fork_id: u8, pk: Vec<u8>) {
+        let reporters = self.reports.entry((cid, epoch, fork_id)).or_default();
+        if !reporters.contains(&pk) {
+            reporters.push(pk);
+        }
+    }
+
+    /// Compute the epoch transition of committee `cid`, return None if the committee not exists.
+    pub async fn transition(
+        &self,
+        sub_client: &BoolSubClient,
+        cid: u32,
+        at_block: Option<Hash>,
+    ) -> Result<Option<EpochTransition>, subxt::Error> {
+        let block_hash = sub_client.block_hash_or_latest(at_block).await?;
+        let Some(committee) = committees(sub_client, cid, Some(block_hash)).await? else {
+            return Ok(None);
+        };
+        let block_number = sub_client.client.read().await.blocks().at(block_hash).await?.number();
+        let config = epoch_config(sub_client, Some(block_hash)).await?;
+        let mut forks = Vec::new();
+        for fork_id in fork_ids(committee.fork) {
+            let members = committee_members(sub_client, cid, committee.epoch, fork_id, Some(block_hash)).await?;
+            let failures = epoch_change_failures(sub_client, cid, fork_id, Some(block_hash)).await?;
+            if members.is_none() && failures == 0 {
+                continue;
+            }
+            forks.push(ForkTransition {
+                fork_id,
+                members: members.unwrap_or_default(),
+                reporters: self.reports.get(&(cid, committee.epoch, fork_id)).cloned().unwrap_or_default(),
+                failures,
+            });
+        }
+        Ok(Some(EpochTransition {
+            cid,
+            block_number,
+            global_epoch: global_epoch(sub_client, Some(block_hash)).await?,
+            epoch: committee.epoch,
+            threshold: committee.parameters.t,
+            forks,
+            failure_limit: config.ecf_limit,
+            deadline_block: report_deadline(&config, block_number),
+            next_config: next_epoch_config(sub_client, Some(block_hash)).await?,
+            last_applied: self.applied.get(&cid).copied(),
+            last_handover: self.handovers.get(&cid).copied(),
+        }))
+    }
+
+    /// Alerts of the committee at risk of failing its handover.
+    pub fn alerts(&self, transition: &EpochTransition) -> Vec<EpochAlert> {
+        let cid = transition.cid;
+        let mut alerts = Vec::new();
+        for fork in &transition.forks {
+            if fork.members.is_empty() {
+                alerts.push(EpochAlert::NoMembers { cid, fork_id: fork.fork_id });
+            }
+            if transition.failure_limit > 0
+                && fork.failures.saturating_add(self.alert_config.failure_margin) >= transition.failure_limit
+            {
+                alerts.push(EpochAlert::FailuresNearLimit {
+                    cid,
+                    fork_id: fork.fork_id,
+                    failures: fork.failures,
+                    limit: transition.failure_limit,
+                });
+            }
+            let reported = fork.reporters.iter().filter(|pk| fork.members.contains(pk)).count() as u16;
+            if transition.blocks_to_deadline() <= self.alert_config.deadline_margin && reported < transition.threshold {
+                alerts.push(EpochAlert::ReportsMissing {
+                    cid,
+                    fork_id: fork.fork_id,
+                    reported,
+                    threshold: transition.threshold,
+                    blocks_left: transition.blocks_to_deadline(),
+                });
+            }
+        }
+        alerts
+    }
+
+    /// Check all working committees at `at_block`, log and return the alerts of them.
+    pub async fn check(
+        &self,
+        sub_client: &BoolSubClient,
+        at_block: Option<Hash>,
+    ) -> Result<Vec<(EpochTransition, Vec<EpochAlert>)>, subxt::Error> {
+        let block_hash = sub_client.block_hash_or_latest(at_block).await?;
+        let mut res = Vec::new();
+        for (cid, committee) in committees_iter(sub_client, 300, Some(block_hash)).await? {
+            if !matches!(committee.state, CommitteeState::Working) {
+                continue;
+            }
+            let Some(transition) = self.transition(sub_client, cid, Some(block_hash)).await? else {
+                continue;
+            };
+            let alerts = self.alerts(&transition);
+            for alert in &alerts {
+                log::warn!(target: "epoch_monitor", "committee {cid} epoch {} at risk: {alert:?}", transition.epoch);
+            }
+            res.push((transition, alerts));
+        }
+        Ok(res)
+    }
+}
+
+#[test]
+fn test_next_epoch_boundary() {
+    let config = GlobalConfig { from: 100, epoch_blocks: 50, apply_blocks: 0, rvrf_blocks: 0, change_blocks: 0, slot_number: 0, ecf_limit: 3 };
+    assert_eq!(next_epoch_boundary(&config, 10), 100);
+    assert_eq!(next_epoch_boundary(&config, 100), 150);
+    assert_eq!(next_epoch_boundary(&config, 149), 150);
+    assert_eq!(next_epoch_boundary(&config, 150), 200);
+}
+
+#[test]
+fn test_report_deadline() {
+    let config = GlobalConfig { from: 100, epoch_blocks: 50, apply_blocks: 5, rvrf_blocks: 10, change_blocks: 10, slot_number: 0, ecf_limit: 3 };
+    assert_eq!(report_deadline(&config, 100), 140);
+    assert_eq!(report_deadline(&config, 145), 140);
+    assert_eq!(report_deadline(&config, 150), 190);
+}
+
+#[test]
+fn test_alerts() {
+    let monitor = EpochMonitor::new(AlertConfig { failure_margin: 1, deadline_margin: 20 });
+    let fork = |fork_id, members: Vec<u8>, reporters: Vec<u8>, failures| ForkTransition {
+        fork_id,
+        members: members.into_iter().map(|m| vec![m]).collect(),
+        reporters: reporters.into_iter().map(|r| vec![r]).collect(),
+        failures,
+    };
+    let mut transition = EpochTransition {
+        cid: 1,
+        block_number: 100,
+        global_epoch: 3,
+        epoch: 2,
+        threshold: 2,
+        forks: vec![fork(0, vec![1, 2, 3], vec![1, 2], 0)],
+        failure_limit: 3,
+        deadline_block: 150,
+        next_config: None,
+        last_applied: None,
+        last_handover: None,
+    };
+    assert!(monitor.alerts(&transition).is_empty());
+
+    // reports are only checked in the margin before the deadline, and reporters out of members don't count
+    transition.forks = vec![fork(0, vec![1, 2, 3], vec![1, 4], 1)];
+    assert!(monitor.alerts(&transition).is_empty());
+    transition.block_number = 130;
+    assert_eq!(
+        monitor.alerts(&transition),
+        vec![EpochAlert::ReportsMissing { cid: 1, fork_id: 0, reported: 1, threshold: 2, blocks_left: 20 }]
+    );
+
+    transition.block_number = 100;
+    transition.forks = vec![fork(0, vec![1, 2, 3], vec![1, 2], 2), fork(1, vec![], vec![], 0)];
+    assert_eq!(
+        monitor.alerts(&transition),
+        vec![
+            EpochAlert::FailuresNearLimit { cid: 1, fork_id: 0, failures: 2, limit: 3 },
+            EpochAlert::NoMembers { cid: 1, fork_id: 1 },
+        ]
+    );
+
+    // no failures alert without the limit
+    transition.failure_limit = 0;
+    assert_eq!(monitor.alerts(&transition), vec![EpochAlert::NoMembers { cid: 1, fork_id: 1 }]);
+}
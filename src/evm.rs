@@ -0,0 +1,497 @@
+//! Helpers to sign evm txs and decode their results on Bool node.
+use std::collections::HashMap;
+use sp_core::{H160, H256, U256};
+use crate::bool::runtime_types::ethereum::log::Log;
+use crate::bool::runtime_types::ethereum::transaction::TransactionV2 as EvmTransaction;
+use crate::bool::runtime_types::evm_core::error::ExitReason;
+use crate::client::BoolConfig;
+use subxt::tx::BoolSigner;
+
+/// keccak_256("Error(string)".as_bytes())[..4]
+pub const REVERT_ERROR_SELECTOR: [u8; 4] = [8, 195, 121, 160];
+
+/// Unsigned evm tx message to be signed by `SubClient::sign_evm_transaction`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvmTransactionMessage {
+    Legacy(ethereum::LegacyTransactionMessage),
+    EIP2930(ethereum::EIP2930TransactionMessage),
+    EIP1559(ethereum::EIP1559TransactionMessage),
+}
+
+impl EvmTransactionMessage {
+    pub fn hash(&self) -> H256 {
+        match self {
+            EvmTransactionMessage::Legacy(tx) => tx.hash(),
+            EvmTransactionMessage::EIP2930(tx) => tx.hash(),
+            EvmTransactionMessage::EIP1559(tx) => tx.hash(),
+        }
+    }
+}
+
+/// Signed evm tx.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedEvmTransaction {
+    // tx to be submitted by 'ethereum.transact'
+    pub transaction: EvmTransaction,
+    // raw rlp bytes, with the type prefix for EIP-2930 and EIP-1559 tx
+    pub raw: Vec<u8>,
+    // evm tx hash
+    pub hash: H256,
+}
+
+impl SignedEvmTransaction {
+    /// Build from a signed tx of 'ethereum' crate.
+    pub fn from_signed(tx: ethereum::TransactionV2) -> Result<Self, String> {
+        let raw = ethereum::EnvelopedEncodable::encode(&tx).to_vec();
+        let hash = H256(sp_core::keccak_256(&raw));
+        // runtime evm tx shares the same scale codec with 'ethereum' crate
+        let transaction = <EvmTransaction as codec::Decode>::decode(&mut codec::Encode::encode(&tx).as_slice())
+            .map_err(|e| e.to_string())?;
+        Ok(SignedEvmTransaction { transaction, raw, hash })
+    }
+}
+
+/// Sign a legacy, EIP-2930 or EIP-1559 evm tx by `signer`.
+pub fn sign_evm_transaction(signer: &BoolSigner<BoolConfig>, tx: EvmTransactionMessage) -> Result<SignedEvmTransaction, String> {
+    let sk = signer.signer().serialize();
+    let secret = secp256k1::SecretKey::parse(&sk).map_err(|e| format!("Parse bool signer sk failed for: {:?}", e))?;
+    let signing_message = secp256k1::Message::parse_slice(&tx.hash()[..])
+        .map_err(|e| e.to_string())?;
+    let (signature, recid) = secp256k1::sign(&signing_message, &secret);
+    let rs = signature.serialize();
+    let r = H256::from_slice(&rs[0..32]);
+    let s = H256::from_slice(&rs[32..64]);
+    let recid = recid.serialize();
+    let signed = match tx {
+        EvmTransactionMessage::Legacy(tx) => {
+            // EIP-155 replay protected 'v' if chain id is set
+            let v = match tx.chain_id {
+                Some(chain_id) => chain_id * 2 + 35 + recid as u64,
+                None => 27 + recid as u64,
+            };
+            ethereum::TransactionV2::Legacy(ethereum::LegacyTransaction {
+                nonce: tx.nonce,
+                gas_price: tx.gas_price,
+                gas_limit: tx.gas_limit,
+                action: tx.action,
+                value: tx.value,
+                input: tx.input,
+                signature: ethereum::TransactionSignature::new(v, r, s).ok_or("Invalid legacy evm tx signature")?,
+            })
+        },
+        EvmTransactionMessage::EIP2930(tx) => ethereum::TransactionV2::EIP2930(ethereum::EIP2930Transaction {
+            chain_id: tx.chain_id,
+            nonce: tx.nonce,
+            gas_price: tx.gas_price,
+            gas_limit: tx.gas_limit,
+            action: tx.action,
+            value: tx.value,
+            input: tx.input,
+            access_list: tx.access_list,
+            odd_y_parity: recid != 0,
+            r,
+            s,
+        }),
+        EvmTransactionMessage::EIP1559(tx) => ethereum::TransactionV2::EIP1559(ethereum::EIP1559Transaction {
+            chain_id: tx.chain_id,
+            nonce: tx.nonce,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            gas_limit: tx.gas_limit,
+            action: tx.action,
+            value: tx.value,
+            input: tx.input,
+            access_list: tx.access_list,
+            odd_y_parity: recid != 0,
+            r,
+            s,
+        }),
+    };
+    SignedEvmTransaction::from_signed(signed)
+}
+
+/// Result of an evm tx executed on Bool chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvmExecution {
+    pub block_hash: H256,
+    // substrate extrinsic hash of 'ethereum.transact' or 'ethereum.transact_unsigned'
+    pub extrinsic_hash: Option<H256>,
+    pub transaction_hash: H256,
+    pub from: H160,
+    pub to: H160,
+    pub contract_address: Option<H160>,
+    pub exit_reason: ExitReason,
+    // readable revert reason if the tx is reverted
+    pub revert_reason: Option<String>,
+    pub status_code: u8,
+    pub used_gas: U256,
+    pub logs: Vec<DecodedLog>,
+}
+
+impl EvmExecution {
+    pub fn is_success(&self) -> bool {
+        matches!(self.exit_reason, ExitReason::Succeed(_))
+    }
+
+    /// Return the readable failure reason, `None` for successful execution.
+    pub fn failure(&self) -> Option<String> {
+        match &self.exit_reason {
+            ExitReason::Succeed(_) => None,
+            ExitReason::Revert(_) => Some(self.revert_reason.clone().unwrap_or_else(|| "reverted".to_string())),
+            reason => Some(format!("{reason:?}")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiType {
+    Uint,
+    Address,
+    Bool,
+    FixedBytes(usize),
+    Bytes,
+    String,
+}
+
+impl std::str::FromStr for AbiType {
+    type Err = String;
+    fn from_str(input: &str) -> Result<AbiType, Self::Err> {
+        match input {
+            "address" => Ok(AbiType::Address),
+            "bool" => Ok(AbiType::Bool),
+            "bytes" => Ok(AbiType::Bytes),
+            "string" => Ok(AbiType::String),
+            t if t.starts_with("uint") || t.starts_with("int") => Ok(AbiType::Uint),
+            t if t.starts_with("bytes") => t[5..]
+                .parse::<usize>()
+                .ok()
+                .filter(|len| *len > 0 && *len <= 32)
+                .map(AbiType::FixedBytes)
+                .ok_or(format!("unsupported abi type: {t}")),
+            t => Err(format!("unsupported abi type: {t}")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiValue {
+    Uint(U256),
+    Address(H160),
+    Bool(bool),
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+}
+
+/// An evm event abi, parsed from 'Name(type indexed,type)'.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvmEventAbi {
+    pub name: String,
+    pub signature: String,
+    // (type, indexed)
+    pub inputs: Vec<(AbiType, bool)>,
+}
+
+impl EvmEventAbi {
+    pub fn parse(abi: &str) -> Result<Self, String> {
+        let (name, params) = abi
+            .trim()
+            .strip_suffix(')')
+            .and_then(|s| s.split_once('('))
+            .ok_or(format!("invalid event abi: {abi}"))?;
+        let mut inputs = Vec::new();
+        let mut types = Vec::new();
+        for param in params.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let mut parts = param.split_whitespace();
+            let ty = parts.next().ok_or(format!("invalid event param: {param}"))?;
+            let indexed = parts.next() == Some("indexed");
+            inputs.push((ty.parse::<AbiType>()?, indexed));
+            types.push(ty);
+        }
+        Ok(EvmEventAbi {
+            name: name.to_string(),
+            signature: format!("{name}({})", types.join(",")),
+            inputs,
+        })
+    }
+
+    pub fn topic(&self) -> H256 {
+        H256(sp_core::keccak_256(self.signature.as_bytes()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedLog {
+    pub address: H160,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+    // event name and params if the log matches a registered abi
+    pub event: Option<(String, Vec<AbiValue>)>,
+}
+
+/// Generic decoder of evm logs by the event abis registered by the caller,
+/// ie. `register("Transfer(address indexed,address indexed,uint256)")`. No event abi is built in,
+/// logs of Bool precompiles are decoded only after their event abis are registered.
+#[derive(Clone, Debug, Default)]
+pub struct AbiLogDecoder {
+    events: HashMap<H256, EvmEventAbi>,
+}
+
+impl AbiLogDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, abi: &str) -> Result<(), String> {
+        let event = EvmEventAbi::parse(abi)?;
+        self.events.insert(event.topic(), event);
+        Ok(())
+    }
+
+    pub fn decode(&self, log: &Log) -> DecodedLog {
+        let event = log
+            .topics
+            .first()
+            .and_then(|topic| self.events.get(topic))
+            .and_then(|abi| decode_event(abi, &log.topics[1..], &log.data).map(|params| (abi.name.clone(), params)));
+        DecodedLog {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            event,
+        }
+    }
+}
+
+fn decode_event(abi: &EvmEventAbi, topics: &[H256], data: &[u8]) -> Option<Vec<AbiValue>> {
+    let mut topics = topics.iter();
+    let mut data_index = 0;
+    let mut params = Vec::new();
+    for (ty, indexed) in &abi.inputs {
+        if *indexed {
+            let topic = topics.next()?;
+            // dynamic types are indexed by their hash
+            let value = match ty {
+                AbiType::Bytes | AbiType::String => AbiValue::FixedBytes(topic.0.to_vec()),
+                _ => decode_word(ty, &topic.0)?,
+            };
+            params.push(value);
+        } else {
+            params.push(decode_abi_value(ty, data, data_index)?);
+            data_index += 1;
+        }
+    }
+    Some(params)
+}
+
+fn decode_word(ty: &AbiType, word: &[u8; 32]) -> Option<AbiValue> {
+    match ty {
+        AbiType::Uint => Some(AbiValue::Uint(U256::from_big_endian(word))),
+        AbiType::Address => Some(AbiValue::Address(H160::from_slice(&word[12..]))),
+        AbiType::Bool => Some(AbiValue::Bool(word[31] != 0)),
+        AbiType::FixedBytes(len) => Some(AbiValue::FixedBytes(word[..*len].to_vec())),
+        _ => None,
+    }
+}
+
+fn read_word(data: &[u8], offset: usize) -> Option<[u8; 32]> {
+    let mut word = [0u8; 32];
+    word.copy_from_slice(data.get(offset..offset.checked_add(32)?)?);
+    Some(word)
+}
+
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = U256::from_big_endian(&read_word(data, offset)?);
+    if word > U256::from(usize::MAX) {
+        return None;
+    }
+    Some(word.as_usize())
+}
+
+/// Decode the `index`th value of abi encoded `data`.
+pub fn decode_abi_value(ty: &AbiType, data: &[u8], index: usize) -> Option<AbiValue> {
+    let head = index.checked_mul(32)?;
+    match ty {
+        AbiType::Bytes | AbiType::String => {
+            let offset = read_usize(data, head)?;
+            let len = read_usize(data, offset)?;
+            let start = offset.checked_add(32)?;
+            let bytes = data.get(start..start.checked_add(len)?)?.to_vec();
+            if *ty == AbiType::String {
+                String::from_utf8(bytes).ok().map(AbiValue::String)
+            } else {
+                Some(AbiValue::Bytes(bytes))
+            }
+        }
+        _ => decode_word(ty, &read_word(data, head)?),
+    }
+}
+
+/// Decode revert data of an evm tx into readable reason.
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    if data.is_empty() {
+        return None;
+    }
+    if data.len() >= 4 && data[..4] == REVERT_ERROR_SELECTOR {
+        if let Some(AbiValue::String(reason)) = decode_abi_value(&AbiType::String, &data[4..], 0) {
+            return Some(reason);
+        }
+    }
+    match std::str::from_utf8(data) {
+        Ok(reason) if reason.chars().all(|c| !c.is_control()) => Some(reason.to_string()),
+        _ => Some("0x".to_string() + &hex::encode(data)),
+    }
+}
+
+#[test]
+fn test_decode_revert_reason() {
+    // abi encoded 'Error("invalid signature")'
+    let mut data = REVERT_ERROR_SELECTOR.to_vec();
+    data.extend_from_slice(&H256::from_low_u64_be(32).0);
+    data.extend_from_slice(&H256::from_low_u64_be(17).0);
+    let mut reason = b"invalid signature".to_vec();
+    reason.resize(32, 0);
+    data.extend_from_slice(&reason);
+    assert_eq!(decode_revert_reason(&data), Some("invalid signature".to_string()));
+    assert_eq!(decode_revert_reason(b"unknown committee"), Some("unknown committee".to_string()));
+    assert_eq!(decode_revert_reason(&[]), None);
+}
+
+#[test]
+fn test_event_abi_topic() {
+    let abi = EvmEventAbi::parse("Transfer(address indexed,address indexed,uint256)").unwrap();
+    assert_eq!(abi.signature, "Transfer(address,address,uint256)");
+    assert_eq!(
+        hex::encode(abi.topic().0),
+        "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+    );
+}
+
+#[test]
+fn test_decode_log() {
+    let mut decoder = AbiLogDecoder::new();
+    decoder.register("Transfer(address indexed,address indexed,uint256)").unwrap();
+    decoder.register("Message(uint32 indexed,bytes)").unwrap();
+    let from = H160::from_low_u64_be(0x1001);
+    let to = H160::from_low_u64_be(0x1002);
+    let transfer = Log {
+        address: H160::from_low_u64_be(0x2000),
+        topics: vec![
+            EvmEventAbi::parse("Transfer(address,address,uint256)").unwrap().topic(),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: H256::from_low_u64_be(1_000_000).0.to_vec(),
+    };
+    assert_eq!(
+        decoder.decode(&transfer).event,
+        Some((
+            "Transfer".to_string(),
+            vec![AbiValue::Address(from), AbiValue::Address(to), AbiValue::Uint(U256::from(1_000_000))]
+        ))
+    );
+
+    // the bytes param is abi encoded as offset, length and the padded bytes
+    let mut data = H256::from_low_u64_be(32).0.to_vec();
+    data.extend_from_slice(&H256::from_low_u64_be(3).0);
+    data.extend_from_slice(&[0xaa, 0xbb, 0xcc]);
+    data.resize(96, 0);
+    let message = Log {
+        address: H160::from_low_u64_be(0x2000),
+        topics: vec![EvmEventAbi::parse("Message(uint32,bytes)").unwrap().topic(), H256::from_low_u64_be(7)],
+        data,
+    };
+    assert_eq!(
+        decoder.decode(&message).event,
+        Some(("Message".to_string(), vec![AbiValue::Uint(U256::from(7)), AbiValue::Bytes(vec![0xaa, 0xbb, 0xcc])]))
+    );
+
+    let unknown = Log { topics: vec![H256::repeat_byte(1)], ..transfer };
+    assert_eq!(decoder.decode(&unknown).event, None);
+}
+
+#[test]
+fn test_sign_evm_transaction() {
+    use subxt::tx::{SecretKey, Signer};
+
+    // EIP-155 example key, signer 0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f
+    let signer = BoolSigner::<BoolConfig>::new(SecretKey::parse(&[0x46; 32]).unwrap());
+    let to = H160::repeat_byte(0x35);
+    let access_list = vec![ethereum::AccessListItem { address: to, storage_keys: vec![H256::from_low_u64_be(1)] }];
+    let recover = |message: &EvmTransactionMessage, signed: &SignedEvmTransaction| {
+        let (odd_y_parity, r, s) = match <ethereum::TransactionV2 as ethereum::EnvelopedDecodable>::decode(&signed.raw).unwrap() {
+            ethereum::TransactionV2::Legacy(tx) => (tx.signature.standard_v() != 0, *tx.signature.r(), *tx.signature.s()),
+            ethereum::TransactionV2::EIP2930(tx) => (tx.odd_y_parity, tx.r, tx.s),
+            ethereum::TransactionV2::EIP1559(tx) => (tx.odd_y_parity, tx.r, tx.s),
+        };
+        let signature = secp256k1::Signature::parse_slice(&[r.0, s.0].concat()).unwrap();
+        let recid = secp256k1::RecoveryId::parse(odd_y_parity as u8).unwrap();
+        let pk = secp256k1::recover(&secp256k1::Message::parse(&message.hash().0), &signature, &recid).unwrap();
+        H160::from_slice(&sp_core::keccak_256(&pk.serialize()[1..])[12..])
+    };
+    let cases = vec![
+        // the EIP-155 example, v = 1 * 2 + 35
+        (
+            EvmTransactionMessage::Legacy(ethereum::LegacyTransactionMessage {
+                nonce: U256::from(9),
+                gas_price: U256::from(20_000_000_000u64),
+                gas_limit: U256::from(21000),
+                action: ethereum::TransactionAction::Call(to),
+                value: U256::from(1_000_000_000_000_000_000u64),
+                input: vec![],
+                chain_id: Some(1),
+            }),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+            "33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788",
+        ),
+        (
+            EvmTransactionMessage::Legacy(ethereum::LegacyTransactionMessage {
+                nonce: U256::from(2),
+                gas_price: U256::from(1_000_000_000u64),
+                gas_limit: U256::from(100000),
+                action: ethereum::TransactionAction::Create,
+                value: U256::zero(),
+                input: vec![0x60, 0x00],
+                chain_id: Some(1),
+            }),
+            "f85202843b9aca00830186a0808082600025a00be3260994df6bdee6601f865ab6a5f020747146fa75108094dff43e4bd166daa04615303768c6f5245c7acdffb87ec7c1370c46d5d6a3c6e54a37ecad274aa8c5",
+            "4a7b4df9a1cddde35ff9982fd7269c932194dead0bacee5b2cf8f227b6b0791b",
+        ),
+        (
+            EvmTransactionMessage::EIP2930(ethereum::EIP2930TransactionMessage {
+                chain_id: 1,
+                nonce: U256::zero(),
+                gas_price: U256::from(1_000_000_000u64),
+                gas_limit: U256::from(30000),
+                action: ethereum::TransactionAction::Call(to),
+                value: U256::one(),
+                input: vec![0x12, 0x34],
+                access_list: access_list.clone(),
+            }),
+            "01f8a00180843b9aca0082753094353535353535353535353535353535353535353501821234f838f7943535353535353535353535353535353535353535e1a0000000000000000000000000000000000000000000000000000000000000000180a06c0876ca9708699410f3aa7671b8a68c25898396439b08161d782dac19067723a0169b3a92c39f849da68255d9b6c035570cf9e883b82f34db026d5d148118917c",
+            "e0cb089ce3242c24bee1931103ad120837dfef38f762577be9443307e1ecd97d",
+        ),
+        (
+            EvmTransactionMessage::EIP1559(ethereum::EIP1559TransactionMessage {
+                chain_id: 1,
+                nonce: U256::one(),
+                max_priority_fee_per_gas: U256::from(1_000_000_000u64),
+                max_fee_per_gas: U256::from(2_000_000_000u64),
+                gas_limit: U256::from(30000),
+                action: ethereum::TransactionAction::Call(to),
+                value: U256::one(),
+                input: vec![0x12, 0x34],
+                access_list,
+            }),
+            "02f8a50101843b9aca00847735940082753094353535353535353535353535353535353535353501821234f838f7943535353535353535353535353535353535353535e1a0000000000000000000000000000000000000000000000000000000000000000101a0fcca284378e53b09992ad92fcb7a58d290b2050c6501632c5a1056efdd25e7c7a07f0dc019df2a4ac2ec690ffaf9d9b17b35415624cdc14188e96afe578d62f905",
+            "9251001888bf9d008f0db609f602af36e97ee1bc337ed7c6ef66fd4b20584385",
+        ),
+    ];
+    for (message, raw, hash) in cases {
+        let signed = sign_evm_transaction(&signer, message.clone()).unwrap();
+        assert_eq!(hex::encode(&signed.raw), raw);
+        assert_eq!(hex::encode(signed.hash.0), hash);
+        assert_eq!(recover(&message, &signed), H160(signer.account_id().0));
+    }
+    assert_eq!(H160(signer.account_id().0), H160::from_slice(&hex::decode("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap()));
+}
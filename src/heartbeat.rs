@@ -0,0 +1,269 @@
+//! Heartbeat service answering the challenge of every session for a device.
+use std::future::Future;
+use std::time::Duration;
+use codec::Encode;
+use futures::StreamExt;
+use tokio::sync::mpsc::Sender;
+use sp_core::H256 as Hash;
+use crate::bool::runtime_types::pallet_facility::pallet::DIdentity;
+use crate::bool::runtime_types::pallet_mining::types::OnChainPayload;
+use crate::query::mining::{challenges, max_no_heartbeat_session_count, working_devices};
+use crate::submit::mining::im_online;
+use crate::BoolSubClient;
+
+/// wait before subscribing the best blocks again after the subscription failed.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(3);
+/// default number of blocks before the session end to report a heartbeat not received.
+const DEFAULT_MISSING_MARGIN: u32 = 10;
+
+/// (session, blocks left to the session end) of `block_number`, sessions are `era_block_number` blocks long.
+pub fn session_position(block_number: u32, era_block_number: u32) -> Option<(u32, u32)> {
+    let session = block_number.checked_div(era_block_number)?;
+    Some((session, era_block_number - block_number % era_block_number))
+}
+
+/// `HeartbeatReport::Missing` if the heartbeat of `session` is `required` but not `received` in the last `margin` blocks.
+pub fn missing_heartbeat(session: u32, blocks_left: u32, required: bool, received: bool, margin: u32) -> Option<HeartbeatReport> {
+    (required && !received && blocks_left <= margin).then_some(HeartbeatReport::Missing { session, blocks_left })
+}
+
+/// Consecutive missed sessions after the required heartbeat of session `last` is `received` or not,
+/// with the `HeartbeatReport::Missed` of it.
+pub fn missed_heartbeat(last: u32, received: bool, consecutive_missed: u32, limit: u32) -> (u32, Option<HeartbeatReport>) {
+    if received {
+        return (0, None);
+    }
+    let consecutive = consecutive_missed + 1;
+    (consecutive, Some(HeartbeatReport::Missed { session: last, consecutive, limit }))
+}
+
+/// Heartbeat of a session generated by the user, from the session and encoded challenge.
+#[derive(Clone, Debug, Default)]
+pub struct HeartbeatProof {
+    pub signature: Vec<u8>,
+    pub proof: Vec<u8>,
+    pub enclave: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeartbeatReport {
+    // 'im_online' submitted for the session
+    Sent { session: u32, hash: Hash },
+    // all retries of the session failed
+    SubmitFailed { session: u32, error: String },
+    // the heartbeat of the session is still not received `blocks_left` blocks before the session end
+    Missing { session: u32, blocks_left: u32 },
+    // the heartbeat of the session is not received, the device is punished when `consecutive` reaches `limit`
+    Missed { session: u32, consecutive: u32, limit: u32 },
+}
+
+/// Submit 'im_online' once per session for device `did` with the proof of `generator`,
+/// sessions are `block_number / era_block_number` and checked on every new best block.
+pub struct HeartbeatService<F> {
+    log_target: String,
+    client: BoolSubClient,
+    // (version, pk) of device identity
+    did: (u16, Vec<u8>),
+    generator: F,
+    reporter: Option<Sender<HeartbeatReport>>,
+    // max attempts to submit the heartbeat of a session
+    pub max_attempts: u32,
+    // report `HeartbeatReport::Missing` within the last blocks of session
+    pub missing_margin: u32,
+    session: Option<u32>,
+    // the device is in the working devices of session
+    required: bool,
+    sent: bool,
+    missing_reported: bool,
+    attempts: u32,
+    consecutive_missed: u32,
+}
+
+impl<F, Fut> HeartbeatService<F>
+where
+    F: Fn(u32, Vec<u8>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<HeartbeatProof, String>> + Send,
+{
+    pub fn new(log_target: &str, client: BoolSubClient, did: (u16, Vec<u8>), generator: F) -> Self {
+        HeartbeatService {
+            log_target: log_target.to_string(),
+            client,
+            did,
+            generator,
+            reporter: None,
+            max_attempts: 3,
+            missing_margin: DEFAULT_MISSING_MARGIN,
+            session: None,
+            required: false,
+            sent: false,
+            missing_reported: false,
+            attempts: 0,
+            consecutive_missed: 0,
+        }
+    }
+
+    /// Send the `HeartbeatReport`s to `reporter`.
+    pub fn with_reporter(mut self, reporter: Sender<HeartbeatReport>) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    pub fn run(mut self) {
+        tokio::spawn(async move {
+            log::info!(target: &self.log_target, "Start heartbeat for device: 0x{}......", hex::encode(&self.did.1));
+            loop {
+                let blocks = self.client.client.read().await.blocks().subscribe_best().await;
+                match blocks {
+                    Ok(mut blocks) => {
+                        while let Some(block) = blocks.next().await {
+                            let res = match block {
+                                Ok(block) => self.check(Some(block.hash())).await,
+                                Err(e) => Err(e.to_string()),
+                            };
+                            if let Err(e) = res {
+                                log::error!(target: &self.log_target, "check heartbeat: {e:?}");
+                            }
+                        }
+                        log::warn!(target: &self.log_target, "best block subscription closed");
+                    }
+                    Err(e) => log::error!(target: &self.log_target, "subscribe best blocks: {e:?}"),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+    }
+
+    /// Check the session at `at_block` or the latest block, and submit the heartbeat if it's required and not received.
+    pub async fn check(&mut self, at_block: Option<Hash>) -> Result<(), String> {
+        let block_hash = self.client.block_hash_or_latest(at_block).await.map_err(|e| e.to_string())?;
+        let block_number = self.client.client.read().await.blocks().at(block_hash).await.map_err(|e| e.to_string())?.number();
+        let era_block_number = self
+            .client
+            .query_constant(crate::bool::constants().mining().era_block_number())
+            .await
+            .map_err(|e| e.to_string())?;
+        let (session, blocks_left) =
+            session_position(block_number, era_block_number).ok_or("invalid era block number: 0".to_string())?;
+        if self.session != Some(session) {
+            self.enter_session(session, block_hash).await?;
+        }
+        // the working devices of the current session, as the chain reads them for 'im_online'
+        let devices = match working_devices(&self.client, None, Some(block_hash)).await.map_err(|e| e.to_string())? {
+            Some((_, chain_session)) if chain_session != session => {
+                return Err(format!("session {session} of block {block_number} differs from working devices session {chain_session}"));
+            }
+            Some((devices, _)) => devices,
+            None => Vec::new(),
+        };
+        let device = devices.iter().find(|(did, _)| did.version == self.did.0 && did.pk == self.did.1);
+        self.required = device.is_some();
+        let received = device.map_or(false, |(_, received)| *received);
+        if !self.missing_reported {
+            if let Some(report) = missing_heartbeat(session, blocks_left, self.required, received, self.missing_margin) {
+                self.missing_reported = true;
+                log::warn!(target: &self.log_target, "heartbeat of session {session} not received, {blocks_left} blocks left");
+                self.report(report).await;
+            }
+        }
+        if !self.required || received || self.sent || self.attempts >= self.max_attempts {
+            return Ok(());
+        }
+        let Some(challenge) = challenges(&self.client, session, Some(block_hash)).await.map_err(|e| e.to_string())? else {
+            return Ok(());
+        };
+
+        self.attempts += 1;
+        let res = match (self.generator)(session, challenge.encode()).await {
+            Ok(heartbeat) => {
+                let payload = OnChainPayload {
+                    did: DIdentity { version: self.did.0, pk: self.did.1.clone() },
+                    proof: heartbeat.proof,
+                    session,
+                    signature: heartbeat.signature,
+                    enclave: heartbeat.enclave,
+                };
+                im_online(&self.client, payload).await
+            }
+            Err(e) => Err(e),
+        };
+        match res {
+            Ok(hash) => {
+                self.sent = true;
+                self.report(HeartbeatReport::Sent { session, hash }).await;
+            }
+            Err(error) => {
+                log::warn!(target: &self.log_target, "heartbeat of session {session} failed at attempt {}: {error}", self.attempts);
+                if self.attempts >= self.max_attempts {
+                    self.report(HeartbeatReport::SubmitFailed { session, error }).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // check the heartbeat of the last session, then reset the state for `session`
+    async fn enter_session(&mut self, session: u32, block_hash: Hash) -> Result<(), String> {
+        if let (Some(last), true) = (self.session, self.required) {
+            let received = working_devices(&self.client, Some(last), Some(block_hash))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_or(false, |(devices, _)| {
+                    devices.iter().any(|(did, received)| *received && did.version == self.did.0 && did.pk == self.did.1)
+                });
+            let limit = if received {
+                0
+            } else {
+                max_no_heartbeat_session_count(&self.client, Some(block_hash)).await.map_err(|e| e.to_string())?
+            };
+            let (consecutive_missed, report) = missed_heartbeat(last, received, self.consecutive_missed, limit);
+            self.consecutive_missed = consecutive_missed;
+            if let Some(report) = report {
+                // no punishment if the limit is not set
+                if limit > 0 && consecutive_missed + 1 >= limit {
+                    log::error!(target: &self.log_target, "device missed {consecutive_missed} heartbeats, will be punished at {limit}");
+                }
+                self.report(report).await;
+            }
+        }
+        self.session = Some(session);
+        self.required = false;
+        self.sent = false;
+        self.missing_reported = false;
+        self.attempts = 0;
+        Ok(())
+    }
+
+    async fn report(&self, report: HeartbeatReport) {
+        if let Some(reporter) = &self.reporter {
+            if let Err(e) = reporter.send(report).await {
+                log::error!(target: &self.log_target, "send heartbeat report: {e:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_session_position() {
+    assert_eq!(session_position(0, 100), Some((0, 100)));
+    assert_eq!(session_position(199, 100), Some((1, 1)));
+    assert_eq!(session_position(200, 100), Some((2, 100)));
+    assert_eq!(session_position(5, 0), None);
+}
+
+#[test]
+fn test_missing_heartbeat() {
+    assert_eq!(missing_heartbeat(3, 11, true, false, 10), None);
+    assert_eq!(missing_heartbeat(3, 10, true, false, 10), Some(HeartbeatReport::Missing { session: 3, blocks_left: 10 }));
+    assert_eq!(missing_heartbeat(3, 1, true, true, 10), None);
+    assert_eq!(missing_heartbeat(3, 1, false, false, 10), None);
+}
+
+#[test]
+fn test_missed_heartbeat() {
+    let (consecutive, report) = missed_heartbeat(4, false, 0, 3);
+    assert_eq!((consecutive, report), (1, Some(HeartbeatReport::Missed { session: 4, consecutive: 1, limit: 3 })));
+    let (consecutive, report) = missed_heartbeat(5, false, consecutive, 3);
+    assert_eq!((consecutive, report), (2, Some(HeartbeatReport::Missed { session: 5, consecutive: 2, limit: 3 })));
+    // a received heartbeat resets the count
+    assert_eq!(missed_heartbeat(6, true, consecutive, 3), (0, None));
+}
@@ -1,9 +1,21 @@
 #![deny(unused_crate_dependencies)]
+pub mod asset_report;
+pub mod btc;
+pub mod cache;
 pub mod client;
+pub mod committee_lifecycle;
+pub mod device_agent;
+pub mod dhc;
+pub mod epoch_monitor;
 pub mod event_watcher;
+pub mod evm;
+pub mod heartbeat;
 pub mod monitor_rpc;
 pub mod query;
+pub mod relay_queue;
+pub mod storage;
 pub mod submit;
+pub mod tracker;
 pub mod types;
 pub mod watcher_rpc;
 
@@ -308,7 +320,12 @@ pub(crate) fn handle_custom_error(error: Error) -> String {
     }
 }
 
-fn parse_custom_err_from_string_err(err: String) -> String {
+/// Check if `err` is a `CustomError` decoded by `handle_custom_error`.
+pub(crate) fn is_custom_error(err: &str) -> bool {
+    (0..=u8::MAX).any(|num| convert_to_custom_error(num) == err)
+}
+
+pub(crate) fn parse_custom_err_from_string_err(err: String) -> String {
     // only try to extract 'custom number', will return input if parse error
     let v: Vec<&str> = err.split("Custom error: ").collect();
     if v.len() == 2 {
@@ -1,8 +1,10 @@
+use codec::{Decode, Encode};
 use sp_core::H256 as Hash;
 use crate::bool::runtime_types::pallet_channel::types::{
     BtcCmtType, BtcTxTunnel, Channel, CommitteeFeeConfig, ForcedWithdrawalRecord, RefreshRecord, SourceTXInfo, TaprootPair, TxMessage, UidRecord, XudtInfo, XudtIssueRecord, BtcScriptPair
 };
 use crate::bool::runtime_types::fp_account::AccountId20;
+use crate::storage::{pallet_storage_prefix, StorageReadProof};
 use crate::BoolSubClient;
 
 pub async fn tx_messages(
@@ -15,6 +17,46 @@ pub async fn tx_messages(
     sub_client.query_storage(store, at_block).await
 }
 
+/// `tx_messages` with the read proof of it.
+pub async fn tx_messages_with_proof(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    hash: Hash,
+    at_block: Option<Hash>,
+) -> Result<(Option<TxMessage<u32>>, StorageReadProof), subxt::Error> {
+    let store = crate::bool::storage().channel().tx_messages(cid, hash);
+    sub_client.query_storage_with_proof(store, at_block).await
+}
+
+/// All tx messages of committee `cid` with their hash, read from the 'TxMessages' entries under `cid` at block `at_block`.
+pub async fn tx_messages_of_cid(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    page_size: u32,
+    at_block: Hash,
+) -> Result<Vec<(Hash, TxMessage<u32>)>, subxt::Error> {
+    let metadata = sub_client.client.read().await.metadata();
+    // twox_128(prefix) ++ twox_128(entry) ++ blake2_128_concat(cid), followed by blake2_128_concat(hash)
+    let prefix = [
+        &sp_core::twox_128(pallet_storage_prefix(&metadata, "Channel")?.as_bytes())[..],
+        &sp_core::twox_128(b"TxMessages")[..],
+        &sp_core::blake2_128(&cid.encode())[..],
+        &cid.encode()[..],
+    ]
+    .concat();
+    let key_len = prefix.len() + 16 + 32;
+    sub_client
+        .query_storage_pairs(prefix, page_size, at_block)
+        .await?
+        .into_iter()
+        .filter(|(key, _)| key.len() == key_len)
+        .map(|(key, value)| {
+            let message = TxMessage::<u32>::decode(&mut &value[..])?;
+            Ok::<_, subxt::Error>((Hash::from_slice(&key[key_len - 32..]), message))
+        })
+        .collect()
+}
+
 pub async fn channel_info(
     sub_client: &BoolSubClient,
     channel_id: u32,
@@ -67,16 +109,11 @@ pub async fn btc_committee_type_iter(
     sub_client: &BoolSubClient,
     page_size: u32,
     at_block: Option<Hash>,
-) -> Result<Vec<BtcCmtType>, subxt::Error> {
+) -> Result<Vec<(u32, BtcCmtType)>, subxt::Error> {
     let store = crate::bool::storage().channel().btc_committee_type_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|v| v.1)
-                .collect()
-        })
 }
 
 pub async fn escape_taproot(sub_client: &BoolSubClient, cid: u32, at_block: Option<Hash>) -> Result<Option<TaprootPair>, subxt::Error> {
@@ -88,16 +125,11 @@ pub async fn escape_taproot_iter(
     sub_client: &BoolSubClient,
     page_size: u32,
     at_block: Option<Hash>,
-) -> Result<Vec<TaprootPair>, subxt::Error> {
+) -> Result<Vec<(u32, TaprootPair)>, subxt::Error> {
     let store = crate::bool::storage().channel().escape_taproots_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|v| v.1)
-                .collect()
-        })
 }
 
 pub async fn bound_script(sub_client: &BoolSubClient, cid: u32, at_block: Option<Hash>) -> Result<Option<BtcScriptPair>, subxt::Error> {
@@ -109,16 +141,11 @@ pub async fn bound_script_iter(
     sub_client: &BoolSubClient,
     page_size: u32,
     at_block: Option<Hash>,
-) -> Result<Vec<BtcScriptPair>, subxt::Error> {
+) -> Result<Vec<(u32, BtcScriptPair)>, subxt::Error> {
     let store = crate::bool::storage().channel().bound_scripts_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|v| v.1)
-                .collect()
-        })
 }
 
 pub async fn refresh_record(
@@ -160,6 +187,17 @@ pub async fn uid_consensus_record(
     sub_client.query_storage(store, at_block).await
 }
 
+/// `uid_consensus_record` with the read proof of it.
+pub async fn uid_consensus_record_with_proof(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    uid: Vec<u8>,
+    at_block: Option<Hash>,
+) -> Result<(Option<UidRecord<u32>>, StorageReadProof), subxt::Error> {
+    let store = crate::bool::storage().channel().uid_consensus_record(cid, uid);
+    sub_client.query_storage_with_proof(store, at_block).await
+}
+
 pub async fn committee_fee_data(
     sub_client: &BoolSubClient,
     cid: u32,
@@ -176,18 +214,8 @@ pub async fn committee_fee_data_iter(
 ) -> Result<Vec<(u32, CommitteeFeeConfig)>, subxt::Error> {
     let store = crate::bool::storage().channel().committee_fee_data_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res
-                .into_iter()
-                .map(|(key, value)| {
-                    let mut cid_bytes = [0u8; 4];
-                    cid_bytes.copy_from_slice(&key.0[48..]);
-                    (u32::from_le_bytes(cid_bytes), value)
-                })
-                .collect()
-        })
 }
 
 pub async fn channel_mapping_tick_iter(
@@ -197,18 +225,8 @@ pub async fn channel_mapping_tick_iter(
 ) -> Result<Vec<(u32, Vec<(Vec<u8>, Vec<u8>)>)>, subxt::Error> {
     let store = crate::bool::storage().channel().channel_mapping_tick_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res
-                .into_iter()
-                .map(|(key, value)| {
-                    let mut cid_bytes = [0u8; 4];
-                    cid_bytes.copy_from_slice(&key.0[48..]);
-                    (u32::from_le_bytes(cid_bytes), value)
-                })
-                .collect()
-        })
 }
 
 pub async fn channel_mapping_tick(
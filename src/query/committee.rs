@@ -1,13 +1,22 @@
 use crate::bool::runtime_types::fp_account::AccountId20;
 use crate::bool::runtime_types::pallet_committee::types::{Committee, GlobalConfig};
+use crate::bool::runtime_types::pallet_channel::types::{BtcCmtType, BtcScriptPair, CommitteeFeeConfig, TaprootPair, XudtInfo};
+use crate::storage::{Batches, StorageReadProof};
 use crate::BoolSubClient;
 use sp_core::H256 as Hash;
+use std::ops::RangeInclusive;
 
 pub async fn global_epoch(sub_client: &BoolSubClient, at_block: Option<Hash>) -> Result<u64, subxt::Error> {
     let store = crate::bool::storage().committee().global_epoch();
     sub_client.query_storage_or_default(store, at_block).await
 }
 
+/// Counter of cids assigned by 'create_committee'.
+pub async fn cid(sub_client: &BoolSubClient, at_block: Option<Hash>) -> Result<u32, subxt::Error> {
+    let store = crate::bool::storage().committee().cid();
+    sub_client.query_storage_or_default(store, at_block).await
+}
+
 pub async fn epoch_config(
     sub_client: &BoolSubClient,
     at_block: Option<Hash>,
@@ -37,16 +46,11 @@ pub async fn committees_iter(
     sub_client: &BoolSubClient,
     page_size: u32,
     at_block: Option<Hash>,
-) -> Result<Vec<Committee<AccountId20, u32>>, subxt::Error> {
+) -> Result<Vec<(u32, Committee<AccountId20, u32>)>, subxt::Error> {
     let store = crate::bool::storage().committee().committees_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|v| v.1)
-                .collect()
-        })
 }
 
 pub async fn snapshot(sub_client: &BoolSubClient, at_block: Option<Hash>) -> Result<Vec<Vec<u8>>, subxt::Error> {
@@ -70,6 +74,18 @@ pub async fn committee_members(
     sub_client.query_storage(store, at_block).await
 }
 
+/// `committee_members` with the read proof of it.
+pub async fn committee_members_with_proof(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    epoch: u32,
+    fork_id: u8,
+    at_block: Option<Hash>,
+) -> Result<(Option<Vec<Vec<u8>>>, StorageReadProof), subxt::Error> {
+    let store = crate::bool::storage().committee().committee_members(cid, (epoch, fork_id));
+    sub_client.query_storage_with_proof(store, at_block).await
+}
+
 pub async fn member_links(
     sub_client: &BoolSubClient,
     member: Vec<u8>,
@@ -86,13 +102,8 @@ pub async fn member_links_iter(
 ) -> Result<Vec<(Vec<u8>, u32)>, subxt::Error> {
     let store = crate::bool::storage().committee().member_links_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter(store, page_size, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|(k, v)| (k.0[49..].to_vec(), v))
-                .collect()
-        })
 }
 
 pub async fn candidate_links(
@@ -122,18 +133,9 @@ pub async fn epoch_change_failures_iter(
 ) -> Result<Vec<(u32, u8, u8)>, subxt::Error> {
     let store = crate::bool::storage().committee().epoch_changes_failures_root();
     sub_client
-        .query_storage_value_iter(store, page_size, at_block)
+        .query_storage_key_value_iter::<(u32, u8), _>(store, page_size, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|(k, v)| {
-                    let mut cid_bytes = [0u8; 4];
-                    cid_bytes.copy_from_slice(&k.0[48..52]);
-                    (u32::from_le_bytes(cid_bytes), k.0[68], v)
-
-                })
-                .collect()
-        })
+        .map(|res| res.into_iter().map(|((cid, fork), v)| (cid, fork, v)).collect())
 }
 
 pub async fn committee_randomness(
@@ -177,3 +179,101 @@ pub async fn rewards_for_fork(
         .rewards_for_fork(cid, epoch, fork_id);
     sub_client.query_storage(store, at_block).await
 }
+
+/// Fork ids of a committee, `Committee.fork` is the number of forks besides the original fork 0.
+pub fn fork_ids(fork: u8) -> RangeInclusive<u8> {
+    0..=fork
+}
+
+/// Full state of a committee read at one block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitteeView {
+    pub block_hash: Hash,
+    pub committee: Committee<AccountId20, u32>,
+    // (fork_id, members) for the current epoch of the committee
+    pub members: Vec<(u8, Vec<Vec<u8>>)>,
+    // (fork_id, candidate links)
+    pub candidate_links: Vec<(u8, Vec<u16>)>,
+    pub randomness: Option<u64>,
+    pub btc_committee_type: Option<BtcCmtType>,
+    pub bound_script: Option<BtcScriptPair>,
+    pub escape_taproot: Option<TaprootPair>,
+    pub fee_config: Option<CommitteeFeeConfig>,
+    pub xudt_list: Vec<XudtInfo>,
+    pub assets_consensus: Option<(Vec<u16>, u64, Vec<u8>)>,
+    // (fork_id, (rewards, members)) for the current epoch of the committee
+    pub rewards_for_fork: Vec<(u8, (u128, Vec<Vec<u8>>))>,
+}
+
+/// Read the committee `cid` together with its channel and assets data at one block, return None if the committee not exists.
+pub async fn committee_view(
+    sub_client: &BoolSubClient,
+    cid: u32,
+    at_block: Option<Hash>,
+) -> Result<Option<CommitteeView>, subxt::Error> {
+    let block_hash = sub_client.block_hash_or_latest(at_block).await?;
+    let storage = crate::bool::storage();
+    let (
+        committee,
+        randomness,
+        btc_committee_type,
+        bound_script,
+        escape_taproot,
+        fee_config,
+        xudt_list,
+        assets_consensus,
+    ) = sub_client
+        .query_storage_batch(
+            (
+                storage.committee().committees(cid),
+                storage.committee().c_randomness(cid),
+                storage.channel().btc_committee_type(cid),
+                storage.channel().bound_scripts(cid),
+                storage.channel().escape_taproots(cid),
+                storage.channel().committee_fee_data(cid),
+                storage.channel().committee_xudt_list(cid),
+                storage.committee_assets().committee_assets_consensus(cid),
+            ),
+            Some(block_hash),
+        )
+        .await?;
+    let Some(committee) = committee else {
+        return Ok(None);
+    };
+
+    let (epoch, forks) = (committee.epoch, fork_ids(committee.fork));
+    let (members, candidate_links, rewards_for_fork) = sub_client
+        .query_storage_batch(
+            Batches((
+                forks.clone().map(|fork| storage.committee().committee_members(cid, (epoch, fork))).collect::<Vec<_>>(),
+                forks.clone().map(|fork| storage.committee().candidate_links(cid, fork)).collect::<Vec<_>>(),
+                forks.clone().map(|fork| storage.committee().rewards_for_fork(cid, epoch, fork)).collect::<Vec<_>>(),
+            )),
+            Some(block_hash),
+        )
+        .await?;
+
+    Ok(Some(CommitteeView {
+        block_hash,
+        committee,
+        members: forks.clone().zip(members).filter_map(|(fork, v)| v.map(|v| (fork, v))).collect(),
+        candidate_links: forks.clone().zip(candidate_links).filter_map(|(fork, v)| v.map(|v| (fork, v))).collect(),
+        randomness,
+        btc_committee_type,
+        bound_script,
+        escape_taproot,
+        fee_config,
+        xudt_list: xudt_list.unwrap_or_default(),
+        assets_consensus,
+        rewards_for_fork: forks.zip(rewards_for_fork).filter_map(|(fork, v)| v.map(|v| (fork, v))).collect(),
+    }))
+}
+
+#[test]
+fn test_fork_ids() {
+    // a committee without fork only has the original fork 0
+    assert_eq!(fork_ids(0).collect::<Vec<_>>(), vec![0]);
+    // the max fork keeps the original fork, and doesn't overflow the u8 fork ids
+    let forks = fork_ids(u8::MAX).collect::<Vec<_>>();
+    assert_eq!((forks.len(), forks.first(), forks.last()), (256, Some(&0), Some(&u8::MAX)));
+}
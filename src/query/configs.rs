@@ -32,17 +32,8 @@ pub async fn monitor_delay_tolerance_iter(
 ) -> Result<Vec<(u32, u64)>, subxt::Error> {
     let store = crate::bool::storage().configs().monitor_delay_tolerance_root();
     sub_client
-        .query_storage_value_iter(store, 300, at_block)
+        .query_storage_key_value_iter(store, 300, at_block)
         .await
-        .map(|res| {
-            res.into_iter()
-                .map(|(key, v)| {
-                    let mut cid_bytes = [0u8; 4];
-                    cid_bytes.copy_from_slice(&key.0[48..]);
-                    (u32::from_le_bytes(cid_bytes), v)
-                })
-                .collect()
-        })
 }
 
 pub async fn device_url_map(
@@ -1,5 +1,12 @@
+use codec::{Decode, Encode};
 use crate::BoolSubClient;
-use sp_core::H256 as Hash;
+use crate::bool::runtime_types::ethereum::receipt::ReceiptV3;
+use crate::bool::runtime_types::evm_core::error::ExitReason;
+use crate::bool::runtime_types::fp_account::AccountId20;
+use crate::bool::runtime_types::fp_rpc::TransactionStatus;
+use crate::bool::runtime_types::sp_runtime::DispatchError;
+use crate::evm::{decode_revert_reason, EvmExecution, AbiLogDecoder};
+use sp_core::{H160, H256 as Hash, U256};
 
 pub async fn evm_chain_id(
     sub_client: &BoolSubClient,
@@ -8,3 +15,165 @@ pub async fn evm_chain_id(
     let store = crate::bool::storage().evm_chain_id().chain_id();
     sub_client.query_storage(store, at_block).await
 }
+
+/// Free balance and nonce of evm account `address`.
+pub async fn evm_account(
+    sub_client: &BoolSubClient,
+    address: H160,
+    at_block: Option<Hash>,
+) -> Result<(u128, u32), subxt::Error> {
+    let store = crate::bool::storage().system().account(AccountId20(address.0));
+    sub_client
+        .query_storage_or_default(store, at_block)
+        .await
+        .map(|info| (info.data.free, info.nonce))
+}
+
+pub async fn evm_balance(
+    sub_client: &BoolSubClient,
+    address: H160,
+    at_block: Option<Hash>,
+) -> Result<u128, subxt::Error> {
+    evm_account(sub_client, address, at_block).await.map(|(balance, _)| balance)
+}
+
+pub async fn evm_nonce(
+    sub_client: &BoolSubClient,
+    address: H160,
+    at_block: Option<Hash>,
+) -> Result<u32, subxt::Error> {
+    evm_account(sub_client, address, at_block).await.map(|(_, nonce)| nonce)
+}
+
+pub async fn account_code(
+    sub_client: &BoolSubClient,
+    address: H160,
+    at_block: Option<Hash>,
+) -> Result<Vec<u8>, subxt::Error> {
+    let store = crate::bool::storage().evm().account_codes(address);
+    sub_client.query_storage_or_default(store, at_block).await
+}
+
+pub async fn account_storage(
+    sub_client: &BoolSubClient,
+    address: H160,
+    slot: Hash,
+    at_block: Option<Hash>,
+) -> Result<Hash, subxt::Error> {
+    let store = crate::bool::storage().evm().account_storages(address, slot);
+    sub_client.query_storage_or_default(store, at_block).await
+}
+
+/// Read-only evm call by runtime api 'EthereumRuntimeRPCApi_call', equivalent to 'eth_call'.
+/// Return the output of the call, or the readable reason if the call is failed.
+pub async fn evm_call(
+    sub_client: &BoolSubClient,
+    from: H160,
+    to: H160,
+    data: Vec<u8>,
+    value: U256,
+    gas_limit: U256,
+    at_block: Option<Hash>,
+) -> Result<Vec<u8>, subxt::Error> {
+    let params = (
+        from,
+        to,
+        data,
+        value,
+        gas_limit,
+        Option::<U256>::None, // max_fee_per_gas
+        Option::<U256>::None, // max_priority_fee_per_gas
+        Option::<U256>::None, // nonce
+        false, // estimate
+        Option::<Vec<(H160, Vec<Hash>)>>::None, // access_list
+    );
+    let res = sub_client
+        .call_runtime_api("EthereumRuntimeRPCApi_call", params.encode(), at_block)
+        .await?;
+    // Result<ExecutionInfo, DispatchError>, only decode leading 'exit_reason' and 'value' of 'ExecutionInfo'
+    let input = &mut res.as_slice();
+    match u8::decode(input)? {
+        0 => {
+            let exit_reason = ExitReason::decode(input)?;
+            let value = Vec::<u8>::decode(input)?;
+            match exit_reason {
+                ExitReason::Succeed(_) => Ok(value),
+                ExitReason::Revert(_) => Err(subxt::Error::Other(
+                    decode_revert_reason(&value).unwrap_or_else(|| "reverted".to_string()),
+                )),
+                reason => Err(subxt::Error::Other(format!("{reason:?}"))),
+            }
+        }
+        _ => Err(subxt::Error::Other(format!("{:?}", DispatchError::decode(input)?))),
+    }
+}
+
+pub async fn current_receipts(
+    sub_client: &BoolSubClient,
+    at_block: Option<Hash>,
+) -> Result<Option<Vec<ReceiptV3>>, subxt::Error> {
+    let store = crate::bool::storage().ethereum().current_receipts();
+    sub_client.query_storage(store, at_block).await
+}
+
+pub async fn current_transaction_statuses(
+    sub_client: &BoolSubClient,
+    at_block: Option<Hash>,
+) -> Result<Option<Vec<TransactionStatus>>, subxt::Error> {
+    let store = crate::bool::storage().ethereum().current_transaction_statuses();
+    sub_client.query_storage(store, at_block).await
+}
+
+/// Query the execution result of evm tx `transaction_hash` in block `block_hash`,
+/// by the 'Ethereum::Executed' event and the receipt of 'pallet_ethereum'.
+/// Logs are decoded by the event abis registered in `decoder`.
+pub async fn evm_execution(
+    sub_client: &BoolSubClient,
+    block_hash: Hash,
+    transaction_hash: Hash,
+    decoder: &AbiLogDecoder,
+) -> Result<Option<EvmExecution>, subxt::Error> {
+    let events = sub_client.client.read().await.events().at(block_hash).await?;
+    let mut executed = None;
+    for event in events.find::<crate::bool::ethereum::events::Executed>() {
+        let event = event?;
+        if event.transaction_hash == transaction_hash {
+            executed = Some(event);
+            break;
+        }
+    }
+    let executed = match executed {
+        Some(executed) => executed,
+        None => return Ok(None),
+    };
+    let statuses = current_transaction_statuses(sub_client, Some(block_hash)).await?.unwrap_or_default();
+    let receipts = current_receipts(sub_client, Some(block_hash)).await?.unwrap_or_default();
+    let status = statuses.into_iter().find(|status| status.transaction_hash == transaction_hash);
+    let receipt = status
+        .as_ref()
+        .and_then(|status| receipts.into_iter().nth(status.transaction_index as usize))
+        .map(|receipt| match receipt {
+            ReceiptV3::Legacy(data) | ReceiptV3::EIP2930(data) | ReceiptV3::EIP1559(data) => data,
+        });
+    let (status_code, used_gas, logs) = match receipt {
+        Some(data) => (data.status_code, sp_core::U256(data.used_gas.0), data.logs),
+        None => (0, sp_core::U256::zero(), status.as_ref().map(|s| s.logs.clone()).unwrap_or_default()),
+    };
+    let revert_reason = match executed.exit_reason {
+        crate::bool::runtime_types::evm_core::error::ExitReason::Revert(_) => decode_revert_reason(&executed.extra_data),
+        _ => None,
+    };
+    Ok(Some(EvmExecution {
+        block_hash,
+        extrinsic_hash: None,
+        transaction_hash,
+        from: executed.from,
+        to: executed.to,
+        contract_address: status.and_then(|status| status.contract_address),
+        exit_reason: executed.exit_reason,
+        revert_reason,
+        status_code,
+        used_gas,
+        logs: logs.iter().map(|log| decoder.decode(log)).collect(),
+    }))
+}
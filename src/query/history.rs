@@ -0,0 +1,85 @@
+//! Sample storage values over a range of blocks or time.
+use std::future::Future;
+use sp_core::H256 as Hash;
+use crate::query::{system::block_hash_by_number, timestamp};
+use crate::BoolSubClient;
+
+/// Sample point of a value: (block number, block timestamp in millis, value).
+pub type Sample<T> = (u32, u64, T);
+
+/// Sample `query` at every `step` blocks in `[from, to]`, the `to` block is always sampled.
+/// ie. `sample_by_blocks(&client, from, to, 600, |hash| committee::global_epoch(&client, Some(hash)))`.
+pub async fn sample_by_blocks<T, F, Fut>(
+    sub_client: &BoolSubClient,
+    from: u32,
+    to: u32,
+    step: u32,
+    mut query: F,
+) -> Result<Vec<Sample<T>>, subxt::Error>
+where
+    F: FnMut(Hash) -> Fut,
+    Fut: Future<Output = Result<T, subxt::Error>>,
+{
+    let numbers = sample_numbers(from, to, step);
+    let mut samples = Vec::with_capacity(numbers.len());
+    for number in numbers {
+        let hash = block_hash_by_number(sub_client, number).await?;
+        let now = timestamp::now(sub_client, Some(hash)).await?.unwrap_or_default();
+        samples.push((number, now, query(hash).await?));
+    }
+    Ok(samples)
+}
+
+/// Sample `query` at `points` blocks evenly spread over the time range `[from, to]`(millis),
+/// the blocks are found by binary search of 'Timestamp.Now'.
+/// ie. the size of working devices over the last week with `|hash| mining::working_devices(&client, None, Some(hash))`.
+pub async fn sample_by_time<T, F, Fut>(
+    sub_client: &BoolSubClient,
+    from: u64,
+    to: u64,
+    points: u32,
+    query: F,
+) -> Result<Vec<Sample<T>>, subxt::Error>
+where
+    F: FnMut(Hash) -> Fut,
+    Fut: Future<Output = Result<T, subxt::Error>>,
+{
+    let from_block = timestamp::block_number_at(sub_client, from).await?.unwrap_or(1);
+    let Some(to_block) = timestamp::block_number_at(sub_client, to).await? else {
+        return Ok(vec![]);
+    };
+    sample_by_blocks(sub_client, from_block, to_block, time_sample_step(from_block, to_block, points), query).await
+}
+
+/// Block numbers sampled by `sample_by_blocks`, every `step` blocks in `[from, to]` and the `to` block.
+pub fn sample_numbers(from: u32, to: u32, step: u32) -> Vec<u32> {
+    if from > to {
+        return vec![];
+    }
+    let mut numbers: Vec<u32> = (from..=to).step_by(step.max(1) as usize).collect();
+    if numbers.last() != Some(&to) {
+        numbers.push(to);
+    }
+    numbers
+}
+
+/// Block step of `sample_by_time` to spread `points` samples over blocks `[from_block, to_block]`.
+pub fn time_sample_step(from_block: u32, to_block: u32, points: u32) -> u32 {
+    (to_block.saturating_sub(from_block) / points.saturating_sub(1).max(1)).max(1)
+}
+
+#[test]
+fn test_sample_numbers() {
+    assert_eq!(sample_numbers(10, 20, 5), vec![10, 15, 20]);
+    // the `to` block is always sampled
+    assert_eq!(sample_numbers(10, 21, 5), vec![10, 15, 20, 21]);
+    assert_eq!(sample_numbers(10, 10, 5), vec![10]);
+    assert_eq!(sample_numbers(3, 5, 0), vec![3, 4, 5]);
+    assert_eq!(sample_numbers(20, 10, 5), Vec::<u32>::new());
+
+    // 5 points over 100 blocks
+    assert_eq!(sample_numbers(1, 101, time_sample_step(1, 101, 5)), vec![1, 26, 51, 76, 101]);
+    // more points than blocks
+    assert_eq!(time_sample_step(1, 3, 10), 1);
+    assert_eq!(time_sample_step(1, 101, 1), 100);
+}
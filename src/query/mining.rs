@@ -3,9 +3,12 @@ use crate::bool::runtime_types::{
     fp_account::AccountId20,
     pallet_facility::pallet::DIdentity,
     pallet_mining::types::{DeviceInfo, MonitorState, RegisterData},
+    pallet_rpc::pallet::DeviceInfo as RpcDeviceInfo,
 };
 use crate::BoolSubClient;
+use futures::Stream;
 use sp_core::H256 as Hash;
+use subxt::storage::StorageKey;
 
 pub async fn challenges(
     sub_client: &BoolSubClient,
@@ -21,19 +24,12 @@ pub async fn working_devices(
     session: Option<u32>,
     at_block: Option<Hash>,
 ) -> Result<Option<(Vec<(DIdentity, bool)>, u32)>, subxt::Error> {
+    // read session and devices at the same block, pinned once from client 'read_consistency'
+    let at_block = sub_client.block_hash_or_latest(at_block).await?;
     let session = match session {
         Some(session) => session,
         None => {
-            let client = sub_client.client.read().await.blocks();
-            let current_block = match at_block {
-                Some(hash) => {
-                    client.at(hash).await
-                },
-                None => {
-                    client.at_latest().await
-                }
-            };
-            let current_number = current_block.map(|b| b.number())?;
+            let current_number = sub_client.client.read().await.blocks().at(at_block).await.map(|b| b.number())?;
             let constant_query = crate::bool::constants().mining().era_block_number();
             sub_client.query_constant(constant_query)
                 .await
@@ -41,7 +37,7 @@ pub async fn working_devices(
         }
     };
     let store = crate::bool::storage().mining().working_devices(session);
-    sub_client.query_storage(store, at_block)
+    sub_client.query_storage(store, Some(at_block))
         .await
         .map(|res| res.and_then(|data| Some((data, session))))
 }
@@ -58,15 +54,9 @@ pub async fn device_info(
 pub async fn device_info_iter(
     sub_client: &BoolSubClient,
     at_block: Option<Hash>,
-) -> Result<Vec<DeviceInfo<AccountId20, u32, u128>>, subxt::Error> {
+) -> Result<Vec<(Vec<u8>, DeviceInfo<AccountId20, u32, u128>)>, subxt::Error> {
     let storage_query = crate::bool::storage().mining().devices_root();
-    sub_client.query_storage_value_iter(storage_query, 300, at_block)
-        .await
-        .map(|res| {
-            res.into_iter()
-                .map(|v| v.1)
-                .collect()
-        })
+    sub_client.query_storage_key_value_iter(storage_query, 300, at_block).await
 }
 
 pub async fn device_identity_map(
@@ -85,9 +75,8 @@ pub async fn device_identity_map_iter(
 ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, subxt::Error> {
     let storage_query = crate::bool::storage().mining().device_identity_map_root();
     sub_client
-        .query_storage_value_iter(storage_query, page_size, at_block)
+        .query_storage_key_value_iter(storage_query, page_size, at_block)
         .await
-        .map(|res| res.into_iter().map(|(k, v)| (k.0[49..].to_vec(), v)).collect())
 }
 
 pub async fn device_monitor_state(
@@ -126,16 +115,36 @@ pub async fn device_data(
     sub_client.query_storage(store, at_block).await
 }
 
+pub async fn max_no_heartbeat_session_count(
+    sub_client: &BoolSubClient,
+    at_block: Option<Hash>,
+) -> Result<u32, subxt::Error> {
+    let store = crate::bool::storage().mining().max_no_heartbeat_session_count();
+    sub_client.query_storage_or_default(store, at_block).await
+}
+
 pub async fn devices_iter(
     sub_client: &BoolSubClient,
     page_size: u32,
     at_block: Option<Hash>,
-) -> Result<Vec<DeviceInfo<AccountId20, u32, u128>>, subxt::Error> {
+) -> Result<Vec<(Vec<u8>, DeviceInfo<AccountId20, u32, u128>)>, subxt::Error> {
     let store = crate::bool::storage().mining().devices_root();
     sub_client
-    .query_storage_value_iter(store, page_size, at_block)
+    .query_storage_key_value_iter(store, page_size, at_block)
     .await
-    .map(|res| res.into_iter().map(|(_, v)| v).collect())
+}
+
+/// Stream all devices page by page, pass the last handled device id key as `start_key` to resume.
+pub async fn devices_stream(
+    sub_client: &BoolSubClient,
+    page_size: u32,
+    start_key: Option<Vec<u8>>,
+    at_block: Option<Hash>,
+) -> Result<impl Stream<Item = Result<(StorageKey, DeviceInfo<AccountId20, u32, u128>), subxt::Error>>, subxt::Error> {
+    let store = crate::bool::storage().mining().devices_root();
+    sub_client
+        .query_storage_value_stream(store, page_size, start_key, at_block)
+        .await
 }
 
 pub async fn device_register_data(
@@ -154,7 +163,106 @@ pub async fn device_register_data_iter(
 ) -> Result<Vec<(Vec<u8>, RegisterData)>, subxt::Error> {
     let store = crate::bool::storage().mining().device_register_data_root();
     sub_client
-    .query_storage_value_iter(store, page_size, at_block)
+    .query_storage_key_value_iter(store, page_size, at_block)
     .await
-    .map(|res| res.into_iter().map(|(k, v)| (k.0[49..].to_vec(), v)).collect())
+}
+
+/// Stream all device register data page by page, pass the last handled key as `start_key` to resume.
+pub async fn device_register_data_stream(
+    sub_client: &BoolSubClient,
+    page_size: u32,
+    start_key: Option<Vec<u8>>,
+    at_block: Option<Hash>,
+) -> Result<impl Stream<Item = Result<(StorageKey, RegisterData), subxt::Error>>, subxt::Error> {
+    let store = crate::bool::storage().mining().device_register_data_root();
+    sub_client
+        .query_storage_value_stream(store, page_size, start_key, at_block)
+        .await
+}
+
+/// State of a device across mining, rpc, configs and committee health pallets read at one block.
+/// Device data is keyed by the device id, while the session work and committee data are keyed by the identity
+/// of device in 'Mining.DeviceIdentityMap', and they are empty if the device has no identity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceView {
+    pub block_hash: Hash,
+    pub device_id: Vec<u8>,
+    pub info: Option<DeviceInfo<AccountId20, u32, u128>>,
+    pub identity: Option<Vec<u8>>,
+    pub monitor_state: Option<MonitorState>,
+    pub votes_for_current_epoch: Vec<(AccountId20, u128)>,
+    pub register_data: Option<RegisterData>,
+    pub rpc_info: Option<RpcDeviceInfo<AccountId20, u32>>,
+    pub url: Option<Vec<u8>>,
+    pub state_votes: Vec<u8>,
+    // (session, challenge) for the identity
+    pub identity_challenge: Option<(u32, Vec<u8>)>,
+    // session of the block and whether the identity is working in it
+    pub session: u32,
+    pub is_working: bool,
+    // committee links of the identity
+    pub member_links: u32,
+}
+
+/// Read all state of device `device_id` at one block.
+pub async fn device_view(
+    sub_client: &BoolSubClient,
+    device_id: Vec<u8>,
+    at_block: Option<Hash>,
+) -> Result<DeviceView, subxt::Error> {
+    let block_hash = sub_client.block_hash_or_latest(at_block).await?;
+    let storage = crate::bool::storage();
+    let (info, identity, monitor_state, votes_for_current_epoch, register_data, rpc_info, url, state_votes) = sub_client
+        .query_storage_batch(
+            (
+                storage.mining().devices(device_id.clone()),
+                storage.mining().device_identity_map(device_id.clone()),
+                storage.mining().device_monitor_state(device_id.clone()),
+                storage.mining().device_votes_for_current_epoch(device_id.clone()),
+                storage.mining().device_register_data(device_id.clone()),
+                storage.rpc().devices(device_id.clone()),
+                storage.configs().device_url_map(device_id.clone()),
+                storage.committee_health().state_votes(device_id.clone()),
+            ),
+            Some(block_hash),
+        )
+        .await?;
+
+    let current_number = sub_client.client.read().await.blocks().at(block_hash).await?.number();
+    let era_block_number = sub_client.query_constant(crate::bool::constants().mining().era_block_number()).await?;
+    let session = current_number / era_block_number;
+    let (identity_challenge, member_links, is_working) = match &identity {
+        Some(identity) => {
+            let (identity_challenge, member_links, working_devices) = sub_client
+                .query_storage_batch(
+                    (
+                        storage.committee_health().identity_challenge(identity.clone()),
+                        storage.committee().member_links(identity.clone()),
+                        storage.mining().working_devices(session),
+                    ),
+                    Some(block_hash),
+                )
+                .await?;
+            let is_working = working_devices.unwrap_or_default().iter().any(|(did, _)| &did.pk == identity);
+            (identity_challenge, member_links.unwrap_or_default(), is_working)
+        }
+        None => (None, 0, false),
+    };
+
+    Ok(DeviceView {
+        block_hash,
+        device_id,
+        info,
+        identity,
+        monitor_state,
+        votes_for_current_epoch: votes_for_current_epoch.unwrap_or_default(),
+        register_data,
+        rpc_info,
+        url,
+        state_votes: state_votes.unwrap_or_default(),
+        identity_challenge,
+        session,
+        is_working,
+        member_links,
+    })
 }
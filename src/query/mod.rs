@@ -4,6 +4,7 @@ pub mod committee;
 pub mod configs;
 pub mod ethereum;
 pub mod facility;
+pub mod history;
 pub mod mining;
 pub mod rpc;
 pub mod system;
@@ -9,3 +9,15 @@ pub async fn block_hash(
     let storage_query = crate::bool::storage().system().block_hash(height);
     sub_client.query_storage(storage_query, at_block).await
 }
+
+/// Hash of the canonical block at `number` known by the node.
+pub async fn block_hash_by_number(sub_client: &BoolSubClient, number: u32) -> Result<Hash, subxt::Error> {
+    sub_client
+        .client
+        .read()
+        .await
+        .rpc()
+        .block_hash(Some(number.into()))
+        .await?
+        .ok_or_else(|| subxt::Error::Other(format!("block hash of {number} not found")))
+}
@@ -1,3 +1,4 @@
+use std::future::Future;
 use sp_core::H256 as Hash;
 use crate::BoolSubClient;
 
@@ -8,3 +9,55 @@ pub async fn now(
     let storage_query = crate::bool::storage().timestamp().now();
     sub_client.query_storage(storage_query, at_block).await
 }
+
+/// Binary search the number of the last block whose timestamp not later than `timestamp`(millis),
+/// return None if `timestamp` is earlier than block 1.
+pub async fn block_number_at(sub_client: &BoolSubClient, timestamp: u64) -> Result<Option<u32>, subxt::Error> {
+    let best = sub_client.client.read().await.blocks().at_latest().await?.number();
+    search_block_number(best, timestamp, |number| async move {
+        let hash = crate::query::system::block_hash_by_number(sub_client, number).await?;
+        now(sub_client, Some(hash)).await
+    })
+    .await
+}
+
+/// Binary search in blocks `[1, best]` by `timestamp_of` block number, the search of `block_number_at`.
+pub async fn search_block_number<F, Fut>(best: u32, timestamp: u64, mut timestamp_of: F) -> Result<Option<u32>, subxt::Error>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<Option<u64>, subxt::Error>>,
+{
+    // genesis block has no timestamp, search in [1, best]
+    let (mut low, mut high) = (1u32, best);
+    let mut found = None;
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        match timestamp_of(mid).await? {
+            Some(now) if now <= timestamp => {
+                found = Some(mid);
+                low = mid + 1;
+            }
+            _ => high = mid - 1,
+        }
+    }
+    Ok(found)
+}
+
+#[tokio::test]
+async fn test_search_block_number() {
+    // block n is produced at 1000 + 6000 * n, block 100 is the head
+    let search = |timestamp| search_block_number(100, timestamp, |number| async move { Ok(Some(1000 + 6000 * number as u64)) });
+    // before block 1
+    assert_eq!(search(0).await.unwrap(), None);
+    assert_eq!(search(6999).await.unwrap(), None);
+    // exact timestamps of the first, a middle and the head block
+    assert_eq!(search(7000).await.unwrap(), Some(1));
+    assert_eq!(search(1000 + 6000 * 37).await.unwrap(), Some(37));
+    assert_eq!(search(1000 + 6000 * 100).await.unwrap(), Some(100));
+    // between two blocks
+    assert_eq!(search(1000 + 6000 * 37 + 5999).await.unwrap(), Some(37));
+    // after the head
+    assert_eq!(search(u64::MAX).await.unwrap(), Some(100));
+    // a chain with only the genesis block
+    assert_eq!(search_block_number(0, u64::MAX, |_| async { Ok(Some(0)) }).await.unwrap(), None);
+}
@@ -0,0 +1,282 @@
+//! Durable queue of `NeedSignedExtrinsic` for the relayer, with the worker draining it to the chain.
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use crate::monitor_rpc::dispatch_extrinsic;
+use crate::submit::SubmitRoute;
+use crate::types::NeedSignedExtrinsic;
+use crate::{is_custom_error, parse_custom_err_from_string_err, BoolSubClient};
+
+/// number of delivered ids remembered for dedup.
+const MAX_DELIVERED_IDS: usize = 4096;
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    // delay after the first failed attempt, doubled on every following failure
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // move the extrinsic to dead letters after failed attempts, None for retrying until delivered (default),
+    // extrinsics rejected with a `CustomError` are moved to dead letters at once by `RelayWorker`
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            base_delay: Duration::from_secs(3),
+            max_delay: Duration::from_secs(300),
+            max_attempts: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempts.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedExtrinsic {
+    pub extrinsic: NeedSignedExtrinsic,
+    pub attempts: u32,
+    // unix millis of the next attempt
+    pub next_attempt: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueState {
+    pending: BTreeMap<u32, QueuedExtrinsic>,
+    dead_letters: BTreeMap<u32, QueuedExtrinsic>,
+    delivered: VecDeque<u32>,
+}
+
+/// File backed queue with at-least-once delivery, an extrinsic stays in the file until it's acked,
+/// so the extrinsics in flight when the process crashed are submitted again after `open`.
+/// Extrinsics are deduplicated by `id` over pending, dead letters and the recently delivered ones.
+#[derive(Debug)]
+pub struct RelayQueue {
+    path: PathBuf,
+    state: QueueState,
+}
+
+impl RelayQueue {
+    /// Open the queue persisted at `path`, or create an empty one if the file doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => QueueState::default(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(RelayQueue { path, state })
+    }
+
+    /// Push `extrinsic` to the queue, return false if the `id` is already queued or delivered.
+    pub fn push(&mut self, extrinsic: NeedSignedExtrinsic) -> Result<bool, String> {
+        let id = extrinsic.id;
+        if self.contains(id) {
+            return Ok(false);
+        }
+        self.state.pending.insert(id, QueuedExtrinsic { extrinsic, attempts: 0, next_attempt: 0, last_error: None });
+        self.persist()?;
+        Ok(true)
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.state.pending.contains_key(&id) || self.state.dead_letters.contains_key(&id) || self.state.delivered.contains(&id)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &QueuedExtrinsic> {
+        self.state.pending.values()
+    }
+
+    pub fn dead_letters(&self) -> impl Iterator<Item = &QueuedExtrinsic> {
+        self.state.dead_letters.values()
+    }
+
+    /// Return the pending extrinsic which waits longest for its attempt at `now` (unix millis).
+    pub fn next_ready(&self, now: u64) -> Option<&QueuedExtrinsic> {
+        self.state.pending.values().filter(|e| e.next_attempt <= now).min_by_key(|e| e.next_attempt)
+    }
+
+    /// Remove the delivered extrinsic `id`.
+    pub fn ack(&mut self, id: u32) -> Result<(), String> {
+        if self.state.pending.remove(&id).is_some() {
+            self.state.delivered.push_back(id);
+            if self.state.delivered.len() > MAX_DELIVERED_IDS {
+                self.state.delivered.pop_front();
+            }
+        }
+        self.persist()
+    }
+
+    /// Record the failed attempt of `id`, and schedule the next attempt by `policy`.
+    pub fn retry(&mut self, id: u32, error: String, policy: &RetryPolicy, now: u64) -> Result<(), String> {
+        let Some(queued) = self.state.pending.get_mut(&id) else {
+            return Ok(());
+        };
+        queued.attempts += 1;
+        queued.next_attempt = now + policy.delay(queued.attempts).as_millis() as u64;
+        queued.last_error = Some(error.clone());
+        if policy.max_attempts.map_or(false, |max| queued.attempts >= max) {
+            return self.dead_letter(id, error);
+        }
+        self.persist()
+    }
+
+    /// Move the permanently invalid extrinsic `id` to dead letters, it won't be submitted again.
+    pub fn dead_letter(&mut self, id: u32, error: String) -> Result<(), String> {
+        if let Some(mut queued) = self.state.pending.remove(&id) {
+            queued.last_error = Some(error);
+            self.state.dead_letters.insert(id, queued);
+        }
+        self.persist()
+    }
+
+    /// Move the dead letter `id` back to pending, ie. after the chain state is fixed manually.
+    pub fn requeue_dead_letter(&mut self, id: u32) -> Result<bool, String> {
+        let Some(mut queued) = self.state.dead_letters.remove(&id) else {
+            return Ok(false);
+        };
+        queued.attempts = 0;
+        queued.next_attempt = 0;
+        self.state.pending.insert(id, queued);
+        self.persist()?;
+        Ok(true)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.state).map_err(|e| e.to_string())?;
+        // write to temp file then rename, so a crash never leaves a broken queue file
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+/// Drain the `RelayQueue` by `dispatch_extrinsic` with `route`.
+/// Extrinsics rejected with a decoded `CustomError` are moved to dead letters, others are retried with backoff.
+#[derive(Clone)]
+pub struct RelayWorker {
+    log_target: String,
+    client: BoolSubClient,
+    queue: Arc<Mutex<RelayQueue>>,
+    pub route: SubmitRoute,
+    pub policy: RetryPolicy,
+    // wait for the result of substrate submission, ignored by the evm route
+    pub need_watch_res: bool,
+    pub poll_interval: Duration,
+}
+
+impl RelayWorker {
+    pub fn new(log_target: &str, client: BoolSubClient, queue: Arc<Mutex<RelayQueue>>) -> Self {
+        RelayWorker {
+            log_target: log_target.to_string(),
+            client,
+            queue,
+            route: SubmitRoute::default(),
+            policy: RetryPolicy::default(),
+            need_watch_res: true,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn run(self) {
+        tokio::spawn(async move {
+            log::info!(target: &self.log_target, "Start relay worker by route: {:?}......", self.route);
+            loop {
+                if !self.process_next().await {
+                    tokio::time::sleep(self.poll_interval).await;
+                }
+            }
+        });
+    }
+
+    /// Submit the next ready extrinsic, return false if there's nothing to submit.
+    pub async fn process_next(&self) -> bool {
+        let Some(queued) = self.queue.lock().await.next_ready(now_millis()).cloned() else {
+            return false;
+        };
+        let id = queued.extrinsic.id;
+        let res = dispatch_extrinsic(&self.client, queued.extrinsic, self.route, self.need_watch_res)
+            .await
+            // signed routes return the raw rpc error, decode the custom error of it
+            .map_err(parse_custom_err_from_string_err);
+        let mut queue = self.queue.lock().await;
+        let persisted = match res {
+            Ok(hash) => {
+                log::info!(target: &self.log_target, "relayed extrinsic {id} in tx: {hash}");
+                queue.ack(id)
+            }
+            Err(e) if is_custom_error(&e) => {
+                log::error!(target: &self.log_target, "relay extrinsic {id} rejected, move to dead letters: {e}");
+                queue.dead_letter(id, e)
+            }
+            Err(e) => {
+                log::warn!(target: &self.log_target, "relay extrinsic {id} failed after {} attempts: {e}", queued.attempts + 1);
+                queue.retry(id, e, &self.policy, now_millis())
+            }
+        };
+        if let Err(e) = persisted {
+            log::error!(target: &self.log_target, "persist relay queue failed for: {e}");
+        }
+        true
+    }
+}
+
+#[test]
+fn test_relay_queue() {
+    use crate::types::{ExtrinsicData, PackageData};
+
+    let path = std::env::temp_dir().join(format!("relay_queue_{}.json", std::process::id()));
+    let extrinsic = |id| NeedSignedExtrinsic { id, data: ExtrinsicData::SyncStatus(PackageData::default()) };
+    let policy = RetryPolicy { max_attempts: Some(3), ..Default::default() };
+
+    let mut queue = RelayQueue::open(&path).unwrap();
+    assert!(queue.push(extrinsic(1)).unwrap());
+    assert!(queue.push(extrinsic(2)).unwrap());
+    assert!(!queue.push(extrinsic(1)).unwrap());
+
+    queue.retry(1, "timeout".to_string(), &policy, 1000).unwrap();
+    assert_eq!(queue.next_ready(1000).unwrap().extrinsic.id, 2);
+    queue.ack(2).unwrap();
+    assert!(!queue.push(extrinsic(2)).unwrap());
+    assert!(queue.next_ready(1000).is_none());
+    assert_eq!(queue.next_ready(4000).unwrap().extrinsic.id, 1);
+
+    // reopen from file
+    let mut queue = RelayQueue::open(&path).unwrap();
+    assert_eq!(queue.pending().next().unwrap().attempts, 1);
+    queue.retry(1, "timeout".to_string(), &policy, 4000).unwrap();
+    queue.retry(1, "timeout".to_string(), &policy, 10000).unwrap();
+    assert_eq!(queue.pending().count(), 0);
+    assert_eq!(queue.dead_letters().count(), 1);
+    assert!(queue.requeue_dead_letter(1).unwrap());
+    assert_eq!(queue.next_ready(0).unwrap().attempts, 0);
+
+    // transport errors are retried until delivered by default
+    for attempt in 0..20 {
+        queue.retry(1, "timeout".to_string(), &RetryPolicy::default(), attempt * 300_000).unwrap();
+    }
+    assert_eq!(queue.pending().next().unwrap().attempts, 20);
+    assert_eq!(queue.dead_letters().count(), 0);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_custom_error_classification() {
+    let raw = r#"Rpc error: client error: Call(Custom(ErrorObject { code: ServerError(1010), message: "Invalid Transaction", data: Some(RawValue("Custom error: 3")) }))"#;
+    assert!(is_custom_error(&parse_custom_err_from_string_err(raw.to_string())));
+    assert!(!is_custom_error(&parse_custom_err_from_string_err("Rpc error: request timeout".to_string())));
+}
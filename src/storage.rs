@@ -0,0 +1,458 @@
+//! Storage helpers driven by the node metadata.
+use std::collections::BTreeMap;
+use codec::{Decode, Encode};
+use sp_core::H256 as Hash;
+use sp_state_machine::{read_proof_check, StorageProof as TrieProof};
+use subxt::config::{substrate::{BlakeTwo256, SubstrateHeader}, Header};
+use subxt::ext::scale_value::{scale::decode_as_type, Value};
+use subxt::metadata::{DecodeWithMetadata, Metadata, StorageEntryType, StorageHasher};
+use subxt::storage::{address::Yes, StorageAddress};
+use subxt::Error;
+use crate::BoolSubClient;
+
+/// Length of the twox_128(storage prefix) ++ twox_128(entry) prefix of storage keys.
+const STORAGE_PREFIX_LEN: usize = 32;
+
+/// Storage prefix of `pallet` in metadata, which may differ from the pallet name.
+pub fn pallet_storage_prefix<'a>(metadata: &'a Metadata, pallet: &str) -> Result<&'a str, Error> {
+    metadata
+        .pallet_by_name(pallet)
+        .and_then(|p| p.storage())
+        .map(|s| s.prefix())
+        .ok_or_else(|| Error::Other(format!("storage of {pallet} not found in metadata")))
+}
+
+/// Full storage key of `address`, twox_128(storage prefix) ++ twox_128(entry) ++ the hashed keys.
+pub fn storage_address_bytes<Address: StorageAddress>(address: &Address, metadata: &Metadata) -> Result<Vec<u8>, Error> {
+    let mut bytes = [
+        sp_core::twox_128(pallet_storage_prefix(metadata, address.pallet_name())?.as_bytes()),
+        sp_core::twox_128(address.entry_name().as_bytes()),
+    ]
+    .concat();
+    address.append_entry_bytes(metadata, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode the raw value of storage `address` as its target type.
+pub fn decode_storage_value<Address: StorageAddress>(
+    address: &Address,
+    data: &[u8],
+    metadata: &Metadata,
+) -> Result<Address::Target, Error> {
+    let (pallet, entry) = (address.pallet_name(), address.entry_name());
+    let value_ty = match metadata
+        .pallet_by_name(pallet)
+        .and_then(|p| p.storage())
+        .and_then(|s| s.entry_by_name(entry))
+        .map(|e| e.entry_type())
+    {
+        Some(StorageEntryType::Plain(value_ty)) => *value_ty,
+        Some(StorageEntryType::Map { value_ty, .. }) => *value_ty,
+        None => return Err(Error::Other(format!("storage {pallet}.{entry} not found in metadata"))),
+    };
+    <Address::Target as DecodeWithMetadata>::decode_with_metadata(&mut &data[..], value_ty, metadata)
+}
+
+/// Decode the default value of storage `address`, which is returned when the storage is empty.
+pub fn decode_storage_default<Address: StorageAddress>(address: &Address, metadata: &Metadata) -> Result<Address::Target, Error> {
+    let (pallet, entry) = (address.pallet_name(), address.entry_name());
+    let default = metadata
+        .pallet_by_name(pallet)
+        .and_then(|p| p.storage())
+        .and_then(|s| s.entry_by_name(entry))
+        .map(|e| e.default_bytes())
+        .ok_or_else(|| Error::Other(format!("storage {pallet}.{entry} not found in metadata")))?;
+    decode_storage_value(address, default, metadata)
+}
+
+/// Storage addresses fetched together at one block by `SubClient::query_storage_batch`,
+/// implemented for tuples of up to 12 fetchable addresses and for `Vec` of addresses with the same type,
+/// the output is the tuple (or `Vec`) of their optional values.
+pub trait StorageBatch {
+    type Output;
+
+    fn keys(&self, metadata: &Metadata) -> Result<Vec<Vec<u8>>, Error>;
+
+    /// Decode `values`, which are in the same order as `keys`.
+    fn decode(&self, values: Vec<Option<Vec<u8>>>, metadata: &Metadata) -> Result<Self::Output, Error>;
+}
+
+macro_rules! impl_storage_batch {
+    ($($address:ident $index:tt),+) => {
+        impl<$($address: StorageAddress<IsFetchable = Yes>),+> StorageBatch for ($($address,)+) {
+            type Output = ($(Option<$address::Target>,)+);
+
+            fn keys(&self, metadata: &Metadata) -> Result<Vec<Vec<u8>>, Error> {
+                Ok(vec![$(storage_address_bytes(&self.$index, metadata)?),+])
+            }
+
+            fn decode(&self, values: Vec<Option<Vec<u8>>>, metadata: &Metadata) -> Result<Self::Output, Error> {
+                let mut values = values.into_iter();
+                Ok(($(
+                    match values.next().flatten() {
+                        Some(data) => Some(decode_storage_value(&self.$index, &data, metadata)?),
+                        None => None,
+                    },
+                )+))
+            }
+        }
+    };
+}
+
+impl_storage_batch!(A 0);
+impl_storage_batch!(A 0, B 1);
+impl_storage_batch!(A 0, B 1, C 2);
+impl_storage_batch!(A 0, B 1, C 2, D 3);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_storage_batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+impl<A: StorageAddress<IsFetchable = Yes>> StorageBatch for Vec<A> {
+    type Output = Vec<Option<A::Target>>;
+
+    fn keys(&self, metadata: &Metadata) -> Result<Vec<Vec<u8>>, Error> {
+        self.iter().map(|address| storage_address_bytes(address, metadata)).collect()
+    }
+
+    fn decode(&self, values: Vec<Option<Vec<u8>>>, metadata: &Metadata) -> Result<Self::Output, Error> {
+        self.iter()
+            .zip(values)
+            .map(|(address, data)| data.map(|data| decode_storage_value(address, &data, metadata)).transpose())
+            .collect()
+    }
+}
+
+/// Several batches fetched in one request, ie. `Batches((members, candidate_links))` of two `Vec` batches.
+pub struct Batches<T>(pub T);
+
+macro_rules! impl_batches {
+    ($($batch:ident $index:tt),+) => {
+        impl<$($batch: StorageBatch),+> StorageBatch for Batches<($($batch,)+)> {
+            type Output = ($($batch::Output,)+);
+
+            fn keys(&self, metadata: &Metadata) -> Result<Vec<Vec<u8>>, Error> {
+                Ok([$(self.0.$index.keys(metadata)?),+].concat())
+            }
+
+            fn decode(&self, values: Vec<Option<Vec<u8>>>, metadata: &Metadata) -> Result<Self::Output, Error> {
+                let mut values = values.into_iter();
+                Ok(($({
+                    let len = self.0.$index.keys(metadata)?.len();
+                    self.0.$index.decode(values.by_ref().take(len).collect(), metadata)?
+                },)+))
+            }
+        }
+    };
+}
+
+impl_batches!(A 0, B 1);
+impl_batches!(A 0, B 1, C 2);
+impl_batches!(A 0, B 1, C 2, D 3);
+
+/// Decode the typed key of map storage `pallet.entry` from the full storage key.
+/// `K` is the key type of the map, a tuple if the map has several hashers, ie. `(u32, H256)` for 'Channel.TxMessages'.
+/// Only keys hashed by `Blake2_128Concat`, `Twox64Concat` or `Identity` can be decoded.
+pub fn decode_storage_key<K: Decode>(
+    metadata: &Metadata,
+    pallet: &str,
+    entry: &str,
+    key: &[u8],
+) -> Result<K, Error> {
+    let entry_metadata = metadata
+        .pallet_by_name(pallet)
+        .and_then(|p| p.storage())
+        .and_then(|s| s.entry_by_name(entry))
+        .ok_or_else(|| Error::Other(format!("storage {pallet}.{entry} not found in metadata")))?;
+    let (hashers, key_ty) = match entry_metadata.entry_type() {
+        StorageEntryType::Map { hashers, key_ty, .. } => (hashers, *key_ty),
+        StorageEntryType::Plain(_) => return Err(Error::Other(format!("storage {pallet}.{entry} is not a map"))),
+    };
+    let key = key
+        .get(STORAGE_PREFIX_LEN..)
+        .ok_or_else(|| Error::Other(format!("storage key too short: 0x{}", hex::encode(key))))?;
+    // concat the scale encoded key parts, which is the encoded key tuple
+    let encoded_key = split_key_parts(metadata, hashers, key_ty, key)?
+        .into_iter()
+        .flat_map(|(_, part)| part.to_vec())
+        .collect::<Vec<_>>();
+    K::decode(&mut encoded_key.as_slice()).map_err(Into::into)
+}
+
+/// Split the hashed keys of a map, which follow the storage prefix, into the scale encoded key parts with their type ids.
+fn split_key_parts<'a>(
+    metadata: &Metadata,
+    hashers: &[StorageHasher],
+    key_ty: u32,
+    key: &'a [u8],
+) -> Result<Vec<(u32, &'a [u8])>, Error> {
+    let key_types: Vec<u32> = if hashers.len() == 1 {
+        vec![key_ty]
+    } else {
+        match &metadata
+            .types()
+            .resolve(key_ty)
+            .ok_or_else(|| Error::Other(format!("key type {key_ty} not found in metadata")))?
+            .type_def
+        {
+            scale_info::TypeDef::Tuple(tuple) => tuple.fields.iter().map(|f| f.id).collect(),
+            _ => return Err(Error::Other(format!("key type {key_ty} is not a tuple"))),
+        }
+    };
+    if key_types.len() != hashers.len() {
+        return Err(Error::Other(format!("key type {key_ty} mismatch hashers")));
+    }
+
+    let mut cursor = key;
+    let mut parts = Vec::with_capacity(hashers.len());
+    for (hasher, ty) in hashers.iter().zip(key_types) {
+        let hash_len = match hasher {
+            StorageHasher::Blake2_128Concat => 16,
+            StorageHasher::Twox64Concat => 8,
+            StorageHasher::Identity => 0,
+            hasher => return Err(Error::Other(format!("can't decode key hashed by {hasher:?}"))),
+        };
+        cursor = cursor
+            .get(hash_len..)
+            .ok_or_else(|| Error::Other(format!("storage key too short: 0x{}", hex::encode(key))))?;
+        let start = cursor;
+        decode_as_type(&mut cursor, ty, metadata.types())
+            .map_err(|e| Error::Other(format!("decode storage key failed for: {e:?}")))?;
+        parts.push((ty, &start[..start.len() - cursor.len()]));
+    }
+    Ok(parts)
+}
+
+/// Change of one storage entry between two blocks.
+/// Keys which can't be decoded, ie. hashed by `Blake2_128` or `Twox128`, are one value of the raw hashed key bytes,
+/// and the undecodable values are their raw bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StorageChange {
+    Added { key: Vec<Value<u32>>, value: Value<u32> },
+    Removed { key: Vec<Value<u32>>, value: Value<u32> },
+    Changed { key: Vec<Value<u32>>, from: Value<u32>, to: Value<u32> },
+}
+
+/// All changes of storage item `pallet.entry`, keys of plain storage are empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageItemDiff {
+    pub pallet: String,
+    pub entry: String,
+    pub changes: Vec<StorageChange>,
+}
+
+/// Diff all storage of `pallet` between block `from` and block `to`, ie. "Committee", "Channel" or "Mining".
+/// Keys and values are decoded by the metadata of the client, only the storage items with changes are returned.
+pub async fn pallet_storage_diff(
+    sub_client: &BoolSubClient,
+    pallet: &str,
+    from: Hash,
+    to: Hash,
+    page_size: u32,
+) -> Result<Vec<StorageItemDiff>, Error> {
+    let metadata = sub_client.client.read().await.metadata();
+    let prefix = sp_core::twox_128(pallet_storage_prefix(&metadata, pallet)?.as_bytes()).to_vec();
+    let before = sub_client.query_storage_pairs(prefix.clone(), page_size, from).await?.into_iter().collect();
+    let after = sub_client.query_storage_pairs(prefix, page_size, to).await?.into_iter().collect();
+    diff_storage_pairs(&metadata, pallet, &before, &after)
+}
+
+/// Diff the raw storage `before` and `after` of `pallet` by their full keys, and decode them by `metadata`.
+pub fn diff_storage_pairs(
+    metadata: &Metadata,
+    pallet: &str,
+    before: &BTreeMap<Vec<u8>, Vec<u8>>,
+    after: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Result<Vec<StorageItemDiff>, Error> {
+    let storage = metadata
+        .pallet_by_name(pallet)
+        .and_then(|p| p.storage())
+        .ok_or_else(|| Error::Other(format!("storage of {pallet} not found in metadata")))?;
+    let pallet_prefix = sp_core::twox_128(storage.prefix().as_bytes());
+
+    let mut diffs = Vec::new();
+    for entry in storage.entries() {
+        let entry_prefix = [pallet_prefix, sp_core::twox_128(entry.name().as_bytes())].concat();
+        let in_entry = |key: &&Vec<u8>| key.starts_with(&entry_prefix);
+        let (key_info, value_ty) = match entry.entry_type() {
+            StorageEntryType::Plain(value_ty) => (None, *value_ty),
+            StorageEntryType::Map { hashers, key_ty, value_ty } => (Some((hashers, *key_ty)), *value_ty),
+        };
+        // keys hashed by opaque hashers and undecodable values are kept as the raw bytes
+        let decode_key = |key: &[u8]| -> Vec<Value<u32>> {
+            let Some((hashers, key_ty)) = key_info else {
+                return vec![];
+            };
+            let key = &key[STORAGE_PREFIX_LEN..];
+            split_key_parts(metadata, hashers, key_ty, key)
+                .and_then(|parts| {
+                    parts
+                        .into_iter()
+                        .map(|(ty, mut part)| {
+                            decode_as_type(&mut part, ty, metadata.types())
+                                .map_err(|e| Error::Other(format!("decode storage key failed for: {e:?}")))
+                        })
+                        .collect()
+                })
+                .unwrap_or_else(|e| {
+                    log::debug!(target: "subxt", "keep raw key of {pallet}.{}: {e:?}", entry.name());
+                    vec![Value::from_bytes(key).map_context(|_| key_ty)]
+                })
+        };
+        let decode_value = |data: &[u8]| {
+            decode_as_type(&mut &data[..], value_ty, metadata.types()).unwrap_or_else(|e| {
+                log::debug!(target: "subxt", "keep raw value of {pallet}.{}: {e:?}", entry.name());
+                Value::from_bytes(data).map_context(|_| value_ty)
+            })
+        };
+
+        let mut changes = Vec::new();
+        for key in before.keys().filter(in_entry).chain(after.keys().filter(in_entry).filter(|k| !before.contains_key(*k))) {
+            let change = match (before.get(key), after.get(key)) {
+                (Some(from), Some(to)) if from != to => StorageChange::Changed {
+                    key: decode_key(&key[..]),
+                    from: decode_value(&from[..]),
+                    to: decode_value(&to[..]),
+                },
+                (Some(value), None) => StorageChange::Removed { key: decode_key(&key[..]), value: decode_value(&value[..]) },
+                (None, Some(value)) => StorageChange::Added { key: decode_key(&key[..]), value: decode_value(&value[..]) },
+                _ => continue,
+            };
+            changes.push(change);
+        }
+        if !changes.is_empty() {
+            diffs.push(StorageItemDiff { pallet: pallet.to_string(), entry: entry.name().to_string(), changes });
+        }
+    }
+    Ok(diffs)
+}
+
+/// Proof of a storage value read from the state of block `block_hash`, got by 'state_getReadProof'.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct StorageReadProof {
+    pub block_hash: Hash,
+    pub state_root: Hash,
+    pub key: Vec<u8>,
+    pub proof: Vec<Vec<u8>>,
+}
+
+/// Check `proof` against `state_root` and return the raw value of `key`, without any RPC node.
+pub fn verify_read_proof_raw(state_root: Hash, key: &[u8], proof: Vec<Vec<u8>>) -> Result<Option<Vec<u8>>, Error> {
+    let mut values = read_proof_check::<sp_runtime::traits::BlakeTwo256, _>(state_root, TrieProof::new(proof), [key])
+        .map_err(|e| Error::Other(format!("invalid storage proof for: {e:?}")))?;
+    Ok(values.remove(key).flatten())
+}
+
+/// Verify `proof` of storage `address` offline against `header`, and decode the value by `metadata`.
+pub fn verify_read_proof<Address: StorageAddress>(
+    address: &Address,
+    proof: &StorageReadProof,
+    header: &SubstrateHeader<u32, BlakeTwo256>,
+    metadata: &Metadata,
+) -> Result<Option<Address::Target>, Error> {
+    if header.hash() != proof.block_hash || header.state_root != proof.state_root {
+        return Err(Error::Other(format!("proof is not for block {:?}", header.hash())));
+    }
+    let key = storage_address_bytes(address, metadata)?;
+    if key != proof.key {
+        return Err(Error::Other("proof is not for the storage address".to_string()));
+    }
+    verify_read_proof_raw(header.state_root, &key, proof.proof.clone())?
+        .map(|data| decode_storage_value(address, &data, metadata))
+        .transpose()
+}
+
+#[test]
+fn test_decode_storage_key() {
+    let metadata = Metadata::decode(&mut &include_bytes!("../metadata.scale")[..]).unwrap();
+    let mut key = [sp_core::twox_128(b"Committee"), sp_core::twox_128(b"EpochChangesFailures")].concat();
+    for part in [7u32.encode(), 2u8.encode()] {
+        key.extend(sp_core::blake2_128(&part));
+        key.extend(part);
+    }
+    let res = decode_storage_key::<(u32, u8)>(&metadata, "Committee", "EpochChangesFailures", &key).unwrap();
+    assert_eq!(res, (7, 2));
+}
+
+#[test]
+fn test_pallet_storage_diff() {
+    let metadata = Metadata::decode(&mut &include_bytes!("../metadata.scale")[..]).unwrap();
+    let randomness = |cid: u32| storage_address_bytes(&crate::bool::storage().committee().c_randomness(cid), &metadata).unwrap();
+    let cid = storage_address_bytes(&crate::bool::storage().committee().cid(), &metadata).unwrap();
+    let before = BTreeMap::from([
+        (cid.clone(), 3u32.encode()),
+        (randomness(1), 5u64.encode()),
+        (randomness(2), 7u64.encode()),
+    ]);
+    let after = BTreeMap::from([
+        (cid, 3u32.encode()),
+        (randomness(1), 6u64.encode()),
+        (randomness(3), vec![1, 2, 3]),
+    ]);
+    let diffs = diff_storage_pairs(&metadata, "Committee", &before, &after).unwrap();
+    assert_eq!(diffs.len(), 1);
+    assert_eq!((diffs[0].pallet.as_str(), diffs[0].entry.as_str()), ("Committee", "CRandomness"));
+    let mut changes = diffs[0]
+        .changes
+        .iter()
+        .map(|change| match change {
+            StorageChange::Changed { key, from, to } => ("changed", key[0].as_u128(), from.as_u128(), to.as_u128()),
+            StorageChange::Removed { key, value } => ("removed", key[0].as_u128(), value.as_u128(), None),
+            StorageChange::Added { key, value } => ("added", key[0].as_u128(), value.as_u128(), None),
+        })
+        .collect::<Vec<_>>();
+    changes.sort();
+    // the 3 bytes value can't be decoded as u64, so it's kept as raw bytes
+    assert_eq!(
+        changes,
+        vec![("added", Some(3), None, None), ("changed", Some(1), Some(5), Some(6)), ("removed", Some(2), Some(7), None)]
+    );
+}
+
+#[test]
+fn test_verify_read_proof() {
+    use sp_runtime::traits::BlakeTwo256 as Hasher;
+    use sp_state_machine::{prove_read, InMemoryBackend};
+
+    let metadata = Metadata::decode(&mut &include_bytes!("../metadata.scale")[..]).unwrap();
+    let address = crate::bool::storage().committee().c_randomness(1);
+    let key = storage_address_bytes(&address, &metadata).unwrap();
+    let other = storage_address_bytes(&crate::bool::storage().committee().c_randomness(2), &metadata).unwrap();
+    let state = |value: u64| {
+        InMemoryBackend::<Hasher>::from((
+            vec![(None, vec![(key.clone(), Some(value.encode())), (other.clone(), Some(9u64.encode()))])],
+            sp_core::storage::StateVersion::V1,
+        ))
+    };
+    let backend = state(5);
+    let state_root = *backend.root();
+    let header = |state_root: Hash| SubstrateHeader::<u32, BlakeTwo256> {
+        parent_hash: Hash::zero(),
+        number: 10,
+        state_root,
+        extrinsics_root: Hash::zero(),
+        digest: Default::default(),
+    };
+    let read_proof = |backend: InMemoryBackend<Hasher>, state_root: Hash| StorageReadProof {
+        block_hash: header(state_root).hash(),
+        state_root,
+        key: key.clone(),
+        proof: prove_read(backend, [&key]).unwrap().into_iter_nodes().collect(),
+    };
+
+    let proof = read_proof(backend, state_root);
+    assert_eq!(verify_read_proof(&address, &proof, &header(state_root), &metadata).unwrap(), Some(5));
+    // the proof of a tampered value doesn't match the state root
+    let tampered = StorageReadProof { proof: read_proof(state(6), state_root).proof, ..proof.clone() };
+    assert!(verify_read_proof(&address, &tampered, &header(state_root), &metadata).is_err());
+    // the proof is checked against the root of another state
+    let wrong_root = Hash::repeat_byte(1);
+    let wrong = StorageReadProof { block_hash: header(wrong_root).hash(), state_root: wrong_root, ..proof.clone() };
+    assert!(verify_read_proof(&address, &wrong, &header(wrong_root), &metadata).is_err());
+    // the header of another block
+    assert!(verify_read_proof(&address, &proof, &header(wrong_root), &metadata).is_err());
+}
@@ -1,5 +1,13 @@
-use sp_core::H256 as Hash;
+#![allow(clippy::too_many_arguments)]
+use sp_core::{H160, H256 as Hash};
+use precompile_utils::solidity::codec::Writer as EvmDataWriter;
+use precompile_utils::prelude::UnboundedBytes;
 use crate::bool::runtime_types::pallet_channel::types::{HandleConnection, TxSource, CmtType, TaprootType, XudtStatus};
+use crate::submit::SubmitRoute;
+use crate::watcher_rpc::{
+    CHANNEL_PRECOMPILE_ADDRESS, IMPORT_NEW_SOURCE_HASH_SELECTOR, SUBMIT_REFRESH_RESULT_SELECTOR,
+    SUBMIT_UID_SIGN_RESULT_SELECTOR, SYNC_STATUS_SELECTOR,
+};
 use crate::{BoolSubClient, handle_custom_error};
 
 pub async fn create_channel(
@@ -57,6 +65,33 @@ pub async fn import_new_src_hash(
     }
 }
 
+/// `need_watch_res` and `nonce` are for the substrate route, the evm route carries its own.
+pub async fn import_new_src_hash_with_route(
+    client: &BoolSubClient,
+    route: SubmitRoute,
+    cid: u32,
+    hash: Vec<u8>,
+    src_chain_id: u32,
+    uid: Vec<u8>,
+    need_watch_res: bool,
+    nonce: Option<u32>,
+) -> Result<Hash, String> {
+    match route {
+        SubmitRoute::Substrate => import_new_src_hash(client, cid, hash, src_chain_id, uid, need_watch_res, nonce).await,
+        SubmitRoute::Evm { watch, nonce } => {
+            let input = EvmDataWriter::new_with_selector(u32::from_be_bytes(IMPORT_NEW_SOURCE_HASH_SELECTOR))
+                .write(cid)
+                .write(UnboundedBytes::from(hash))
+                .write(src_chain_id)
+                .write(UnboundedBytes::from(uid))
+                .build();
+            client
+                .submit_evm_call_with_signer(H160::from_low_u64_be(CHANNEL_PRECOMPILE_ADDRESS), input, watch, nonce)
+                .await
+        }
+    }
+}
+
 pub async fn report_result(
     client: &BoolSubClient,
     pk: Vec<u8>,
@@ -108,6 +143,29 @@ pub async fn sync_status(
     }
 }
 
+/// `watch_res` and `nonce` are for the substrate route, the evm route carries its own.
+pub async fn sync_status_with_route(
+    client: &BoolSubClient,
+    route: SubmitRoute,
+    cid: u32,
+    hash: Vec<u8>,
+    watch_res: bool,
+    nonce: Option<u32>,
+) -> Result<Hash, String> {
+    match route {
+        SubmitRoute::Substrate => sync_status(client, cid, hash, watch_res, nonce).await,
+        SubmitRoute::Evm { watch, nonce } => {
+            let input = EvmDataWriter::new_with_selector(u32::from_be_bytes(SYNC_STATUS_SELECTOR))
+                .write(cid)
+                .write(UnboundedBytes::from(hash))
+                .build();
+            client
+                .submit_evm_call_with_signer(H160::from_low_u64_be(CHANNEL_PRECOMPILE_ADDRESS), input, watch, nonce)
+                .await
+        }
+    }
+}
+
 pub async fn clear_target_package(
     client: &BoolSubClient,
     cid: u32,
@@ -195,6 +253,45 @@ pub async fn submit_refresh_result_call_bytes(
     client.unsigned_tx_encode_to_bytes(call).await.map_err(handle_custom_error)
 }
 
+pub async fn submit_refresh_result_with_route(
+    client: &BoolSubClient,
+    route: SubmitRoute,
+    cid: u32,
+    inscription_tx: Vec<u8>,
+    inscription_pos: u8,
+    sender_pk: Vec<u8>,
+    sender_sig: Vec<u8>,
+    cmt_sig: Vec<u8>,
+    fork_id: u8,
+) -> Result<Hash, String> {
+    match route {
+        SubmitRoute::Substrate => submit_refresh_result(
+            client,
+            cid,
+            inscription_tx,
+            inscription_pos,
+            sender_pk,
+            sender_sig,
+            cmt_sig,
+            fork_id,
+        ).await,
+        SubmitRoute::Evm { watch, nonce } => {
+            let input = EvmDataWriter::new_with_selector(u32::from_be_bytes(SUBMIT_REFRESH_RESULT_SELECTOR))
+                .write(cid)
+                .write(UnboundedBytes::from(inscription_tx))
+                .write(inscription_pos)
+                .write(UnboundedBytes::from(sender_pk))
+                .write(UnboundedBytes::from(sender_sig))
+                .write(UnboundedBytes::from(cmt_sig))
+                .write(fork_id)
+                .build();
+            client
+                .submit_evm_call_with_signer(H160::from_low_u64_be(CHANNEL_PRECOMPILE_ADDRESS), input, watch, nonce)
+                .await
+        }
+    }
+}
+
 pub async fn sign_issue_xudt(
     client: &BoolSubClient,
     cid: u32,
@@ -320,6 +417,34 @@ pub async fn submit_uid_sign_result_call_bytes(
     client.unsigned_tx_encode_to_bytes(call).await.map_err(handle_custom_error)
 }
 
+pub async fn submit_uid_sign_result_with_route(
+    client: &BoolSubClient,
+    route: SubmitRoute,
+    cid: u32,
+    uid: Vec<u8>,
+    pk: Vec<u8>,
+    sig: Vec<u8>,
+    fork_id: u8,
+    signature: Vec<u8>,
+) -> Result<Hash, String> {
+    match route {
+        SubmitRoute::Substrate => submit_uid_sign_result(client, cid, uid, pk, sig, fork_id, signature).await,
+        SubmitRoute::Evm { watch, nonce } => {
+            let input = EvmDataWriter::new_with_selector(u32::from_be_bytes(SUBMIT_UID_SIGN_RESULT_SELECTOR))
+                .write(cid)
+                .write(UnboundedBytes::from(uid))
+                .write(UnboundedBytes::from(pk))
+                .write(UnboundedBytes::from(sig))
+                .write(fork_id)
+                .write(UnboundedBytes::from(signature))
+                .build();
+            client
+                .submit_evm_call_with_signer(H160::from_low_u64_be(CHANNEL_PRECOMPILE_ADDRESS), input, watch, nonce)
+                .await
+        }
+    }
+}
+
 pub async fn request_to_sign_forced_withdrawal(
     client: &BoolSubClient,
     tx_nonce: u128,
@@ -1,6 +1,10 @@
 #![allow(clippy::too_many_arguments)]
-use sp_core::H256 as Hash;
+use sp_core::{H160, H256 as Hash};
+use precompile_utils::solidity::codec::Writer as EvmDataWriter;
+use precompile_utils::prelude::UnboundedBytes;
 use crate::bool::runtime_types::pallet_committee::types::CryptoType;
+use crate::submit::SubmitRoute;
+use crate::watcher_rpc::{COMMITTEE_PRECOMPILE_ADDRESS, REPORT_CHANGE_SELECTOR};
 use crate::{BoolSubClient, handle_custom_error};
 
 pub async fn create_committee(
@@ -103,3 +107,33 @@ pub async fn report_change_call_bytes(
     let call = crate::bool::tx().committee().report_change(pk, sig, cid, epoch, fork_id, signature, pubkey);
     client.unsigned_tx_encode_to_bytes(call).await.map_err(handle_custom_error)
 }
+
+pub async fn report_change_with_route(
+    client: &BoolSubClient,
+    route: SubmitRoute,
+    pk: Vec<u8>,
+    sig: Vec<u8>,
+    cid: u32,
+    epoch: u32,
+    fork_id: u8,
+    signature: Vec<u8>,
+    pubkey: Vec<u8>,
+) -> Result<Hash, String> {
+    match route {
+        SubmitRoute::Substrate => report_change(client, pk, sig, cid, epoch, fork_id, signature, pubkey).await,
+        SubmitRoute::Evm { watch, nonce } => {
+            let input = EvmDataWriter::new_with_selector(u32::from_be_bytes(REPORT_CHANGE_SELECTOR))
+                .write(UnboundedBytes::from(pk))
+                .write(UnboundedBytes::from(sig))
+                .write(cid)
+                .write(epoch)
+                .write(fork_id)
+                .write(UnboundedBytes::from(signature))
+                .write(UnboundedBytes::from(pubkey))
+                .build();
+            client
+                .submit_evm_call_with_signer(H160::from_low_u64_be(COMMITTEE_PRECOMPILE_ADDRESS), input, watch, nonce)
+                .await
+        }
+    }
+}
@@ -1,4 +1,9 @@
-use sp_core::H256 as Hash;
+#![allow(clippy::too_many_arguments)]
+use sp_core::{H160, H256 as Hash};
+use precompile_utils::solidity::codec::Writer as EvmDataWriter;
+use precompile_utils::prelude::UnboundedBytes;
+use crate::submit::SubmitRoute;
+use crate::watcher_rpc::{COMMITTEE_ASSETS_PRECOMPILE_ADDRESS, UPDATE_ASSETS_SELECTOR};
 use crate::{BoolSubClient, handle_custom_error};
 
 pub async fn update_assets(
@@ -30,3 +35,39 @@ pub async fn update_assets_call_bytes(
     let call = crate::bool::tx().committee_assets().update_assets(cid, block_number, btc_asset, brc20_assets, sender_pk, sender_sig, cmt_sig, fork_id);
     client.unsigned_tx_encode_to_bytes(call).await.map_err(handle_custom_error)
 }
+
+pub async fn update_assets_with_route(
+    client: &BoolSubClient,
+    route: SubmitRoute,
+    cid: u32,
+    block_number: u32,
+    btc_asset: u128,
+    brc20_assets: Vec<(Vec<u8>, u128)>,
+    sender_pk: Vec<u8>,
+    sender_sig: Vec<u8>,
+    cmt_sig: Vec<u8>,
+    fork_id: u8,
+) -> Result<Hash, String> {
+    match route {
+        SubmitRoute::Substrate => update_assets(client, cid, block_number, btc_asset, brc20_assets, sender_pk, sender_sig, cmt_sig, fork_id).await,
+        SubmitRoute::Evm { watch, nonce } => {
+            let brc20_assets: Vec<(UnboundedBytes, u128)> = brc20_assets
+                .into_iter()
+                .map(|(tick, amount)| (UnboundedBytes::from(tick), amount))
+                .collect();
+            let input = EvmDataWriter::new_with_selector(u32::from_be_bytes(UPDATE_ASSETS_SELECTOR))
+                .write(cid)
+                .write(block_number)
+                .write(btc_asset)
+                .write(brc20_assets)
+                .write(UnboundedBytes::from(sender_pk))
+                .write(UnboundedBytes::from(sender_sig))
+                .write(UnboundedBytes::from(cmt_sig))
+                .write(fork_id)
+                .build();
+            client
+                .submit_evm_call_with_signer(H160::from_low_u64_be(COMMITTEE_ASSETS_PRECOMPILE_ADDRESS), input, watch, nonce)
+                .await
+        }
+    }
+}
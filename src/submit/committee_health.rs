@@ -36,3 +36,25 @@ pub async fn report_state_vote_call_bytes(
     let call = crate::bool::tx().committee_health().report_state_vote(device_id, sig);
     client.unsigned_tx_encode_to_bytes(call).await.map_err(handle_custom_error)
 }
+
+/// Same as `report_health`, but signed and paid by the client signer.
+pub async fn report_health_signed(
+    client: &BoolSubClient,
+    ident: Vec<u8>,
+    sig: Vec<u8>,
+    nonce: Option<u32>,
+) -> Result<Hash, String> {
+    let call = crate::bool::tx().committee_health().report_health(ident, sig);
+    client.submit_extrinsic_with_signer_and_watch(call, nonce).await.map_err(|e| e.to_string())
+}
+
+/// Same as `report_state_vote`, but signed and paid by the client signer.
+pub async fn report_state_vote_signed(
+    client: &BoolSubClient,
+    device_id: Vec<u8>,
+    sig: Vec<u8>,
+    nonce: Option<u32>,
+) -> Result<Hash, String> {
+    let call = crate::bool::tx().committee_health().report_state_vote(device_id, sig);
+    client.submit_extrinsic_with_signer_and_watch(call, nonce).await.map_err(|e| e.to_string())
+}
@@ -1,6 +1,10 @@
+use codec::Encode;
 use sp_core::H256 as Hash;
+use subxt::tx::TxPayload;
 use crate::{BoolSubClient, handle_custom_error};
 use crate::bool::runtime_types::ethereum::transaction::TransactionV2 as Transaction;
+use crate::evm::{EvmExecution, AbiLogDecoder};
+use crate::query::ethereum::evm_execution;
 
 pub async fn transact(
     client: &BoolSubClient,
@@ -25,3 +29,53 @@ pub async fn transact_unsigned_call_bytes(
     let call = crate::bool::tx().ethereum().transact_unsigned(transaction);
     client.unsigned_tx_encode_to_bytes(call).await.map_err(handle_custom_error)
 }
+
+/// Compute the evm tx hash of `transaction`.
+pub fn evm_transaction_hash(transaction: &Transaction) -> Result<Hash, String> {
+    let tx = <ethereum::TransactionV2 as codec::Decode>::decode(&mut transaction.encode().as_slice())
+        .map_err(|e| e.to_string())?;
+    Ok(Hash(sp_core::keccak_256(&ethereum::EnvelopedEncodable::encode(&tx))))
+}
+
+pub async fn transact_and_watch_execution(
+    client: &BoolSubClient,
+    transaction: Transaction,
+    decoder: &AbiLogDecoder,
+) -> Result<EvmExecution, String> {
+    let transaction_hash = evm_transaction_hash(&transaction)?;
+    let call = crate::bool::tx().ethereum().transact(transaction);
+    watch_execution(client, call, transaction_hash, decoder).await
+}
+
+pub async fn transact_unsigned_and_watch_execution(
+    client: &BoolSubClient,
+    transaction: Transaction,
+    decoder: &AbiLogDecoder,
+) -> Result<EvmExecution, String> {
+    let transaction_hash = evm_transaction_hash(&transaction)?;
+    let call = crate::bool::tx().ethereum().transact_unsigned(transaction);
+    watch_execution(client, call, transaction_hash, decoder).await
+}
+
+async fn watch_execution<Call: TxPayload>(
+    client: &BoolSubClient,
+    call: Call,
+    transaction_hash: Hash,
+    decoder: &AbiLogDecoder,
+) -> Result<EvmExecution, String> {
+    let tx_in_block = client
+        .submit_extrinsic_without_signer_and_watch(call)
+        .await
+        .map_err(handle_custom_error)?
+        .wait_for_in_block()
+        .await
+        .map_err(|e| e.to_string())?;
+    let block_hash = tx_in_block.block_hash();
+    let extrinsic_hash = tx_in_block.extrinsic_hash();
+    tx_in_block.wait_for_success().await.map_err(|e| e.to_string())?;
+    evm_execution(client, block_hash, transaction_hash, decoder)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|execution| EvmExecution { extrinsic_hash: Some(extrinsic_hash), ..execution })
+        .ok_or(format!("no evm execution for tx: {transaction_hash:?} in block: {block_hash:?}"))
+}
@@ -5,3 +5,15 @@ pub mod ethereum;
 pub mod mining;
 pub mod rpc;
 pub mod committee_assets;
+pub mod utility;
+
+/// Route to submit a call to Bool chain.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SubmitRoute {
+    /// submit as a substrate extrinsic.
+    #[default]
+    Substrate,
+    /// submit as an evm tx to the matching Bool precompile contract, signed by the client signer.
+    /// Wait for the tx in block if `watch`, and sign it with `nonce` or the next nonce of the client.
+    Evm { watch: bool, nonce: Option<u32> },
+}
@@ -0,0 +1,171 @@
+use codec::Decode;
+use sp_core::H256 as Hash;
+use subxt::blocks::ExtrinsicEvents;
+use subxt::tx::TxPayload;
+use crate::bool::runtime_types::node_runtime::RuntimeCall;
+use crate::bool::system::events::ExtrinsicFailed;
+use crate::bool::utility::events::{BatchInterrupted, ItemFailed};
+use crate::{BoolConfig, BoolSubClient};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum BatchMode {
+    // 'utility.batch', stop at the first failed call
+    Batch,
+    // 'utility.batch_all', revert all calls if one of them failed
+    #[default]
+    BatchAll,
+    // submit calls one by one with consecutive nonces
+    Pipeline,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchCallOutcome {
+    Completed,
+    Failed(String),
+    // not dispatched since an earlier call of the batch failed
+    NotExecuted,
+    // reverted since the batch extrinsic failed, ie. one call of 'utility.batch_all' failed
+    Reverted(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchResult {
+    // one hash for 'utility' batch, one hash per call for pipeline
+    pub extrinsic_hashes: Vec<Hash>,
+    pub outcomes: Vec<BatchCallOutcome>,
+}
+
+/// Collect calls to submit by one 'utility' batch.
+#[derive(Default)]
+pub struct CallBatch {
+    calls: Vec<Box<dyn TxPayload + Send + Sync>>,
+}
+
+impl CallBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<Call: TxPayload + Send + Sync + 'static>(&mut self, call: Call) -> &mut Self {
+        self.calls.push(Box::new(call));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+}
+
+/// Submit all calls of `batch` signed by the client signer, by `utility.batch`/`utility.batch_all` if the runtime has it,
+/// otherwise pipeline them with consecutive nonces.
+pub async fn submit_batch(
+    client: &BoolSubClient,
+    batch: CallBatch,
+    mode: BatchMode,
+) -> Result<BatchResult, String> {
+    if batch.is_empty() {
+        return Ok(BatchResult::default());
+    }
+    let metadata = client.client.read().await.metadata();
+    let utility_call = match mode {
+        BatchMode::Batch => Some("batch"),
+        BatchMode::BatchAll => Some("batch_all"),
+        BatchMode::Pipeline => None,
+    }
+    .filter(|name| {
+        metadata
+            .pallet_by_name("Utility")
+            .and_then(|pallet| pallet.call_variant_by_name(name))
+            .is_some()
+    });
+    let Some(utility_call) = utility_call else {
+        return submit_pipeline(client, batch).await;
+    };
+
+    let size = batch.len();
+    let mut calls = Vec::with_capacity(size);
+    for call in batch.calls {
+        let call_data = call.encode_call_data(&metadata).map_err(|e| e.to_string())?;
+        calls.push(RuntimeCall::decode(&mut call_data.as_slice()).map_err(|e| e.to_string())?);
+    }
+    let progress = if utility_call == "batch" {
+        let call = crate::bool::tx().utility().batch(calls);
+        client.submit_extrinsic_with_signer_and_progress(call, None).await
+    } else {
+        let call = crate::bool::tx().utility().batch_all(calls);
+        client.submit_extrinsic_with_signer_and_progress(call, None).await
+    }
+    .map_err(|e| e.to_string())?;
+    // fetch the events even if the batch failed, so every call has its outcome
+    let tx = progress.wait_for_in_block().await.map_err(|e| e.to_string())?;
+    let events = tx.fetch_events().await.map_err(|e| e.to_string())?;
+    Ok(BatchResult {
+        extrinsic_hashes: vec![events.extrinsic_hash()],
+        outcomes: batch_outcomes(&events, size)?,
+    })
+}
+
+fn batch_outcomes(events: &ExtrinsicEvents<BoolConfig>, size: usize) -> Result<Vec<BatchCallOutcome>, String> {
+    let mut outcomes = Vec::with_capacity(size);
+    for event in events.iter() {
+        let event = event.map_err(|e| e.to_string())?;
+        // the events of calls are reverted with the failed extrinsic
+        if let Some(failed) = event.as_event::<ExtrinsicFailed>().map_err(|e| e.to_string())? {
+            return Ok(vec![BatchCallOutcome::Reverted(format!("{:?}", failed.dispatch_error)); size]);
+        }
+        if event.pallet_name() != "Utility" {
+            continue;
+        }
+        match event.variant_name() {
+            "ItemCompleted" => outcomes.push(BatchCallOutcome::Completed),
+            "ItemFailed" => {
+                if let Some(failed) = event.as_event::<ItemFailed>().map_err(|e| e.to_string())? {
+                    outcomes.push(BatchCallOutcome::Failed(format!("{:?}", failed.error)));
+                }
+            }
+            "BatchInterrupted" => {
+                if let Some(interrupted) = event.as_event::<BatchInterrupted>().map_err(|e| e.to_string())? {
+                    outcomes.truncate(interrupted.index as usize);
+                    outcomes.push(BatchCallOutcome::Failed(format!("{:?}", interrupted.error)));
+                }
+            }
+            _ => {}
+        }
+    }
+    outcomes.resize(size, BatchCallOutcome::NotExecuted);
+    Ok(outcomes)
+}
+
+async fn submit_pipeline(
+    client: &BoolSubClient,
+    batch: CallBatch,
+) -> Result<BatchResult, String> {
+    // submit all calls before waiting, the nonce of failed call is reused by the next call
+    let mut progresses = Vec::with_capacity(batch.len());
+    for call in batch.calls {
+        progresses.push(client.submit_extrinsic_with_signer_and_progress(call, None).await.map_err(|e| e.to_string()));
+    }
+
+    let mut res = BatchResult::default();
+    for progress in progresses {
+        let outcome = match progress {
+            Ok(progress) => match progress.wait_for_in_block().await {
+                Ok(tx) => {
+                    res.extrinsic_hashes.push(tx.extrinsic_hash());
+                    match tx.wait_for_success().await {
+                        Ok(_) => BatchCallOutcome::Completed,
+                        Err(e) => BatchCallOutcome::Failed(e.to_string()),
+                    }
+                }
+                Err(e) => BatchCallOutcome::Failed(e.to_string()),
+            },
+            Err(e) => BatchCallOutcome::Failed(e),
+        };
+        res.outcomes.push(outcome);
+    }
+    Ok(res)
+}
@@ -0,0 +1,242 @@
+//! Track cross-chain transfers relayed to the channel pallet, from the relay to the final stage.
+use std::collections::{BTreeSet, HashMap};
+use sp_core::H256 as Hash;
+use subxt::events::EventDetails;
+use crate::bool::channel::events::{NewSourceHash, NewTransaction, SubmitTransactionSignResult};
+use crate::bool::runtime_types::pallet_channel::types::TxStatus;
+use crate::query::channel::{hashes_for_cid, source_hash_to_package_key, source_tx_package, tx_messages, tx_messages_of_cid};
+use crate::types::PreparedCrossTransactionData;
+use crate::{BoolConfig, BoolSubClient};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransferStage {
+    // relayed by 'submit_extrinsic' or 'submit_extrinsic_by_evm', not seen on chain yet
+    Submitted,
+    // 'Channel.NewTransaction', the tx message `hash` waits to be signed by the committee
+    Imported { hash: Hash },
+    // 'Channel.SubmitTransactionSignResult', the tx message is signed
+    Signed { hash: Hash },
+    // 'Channel.NewSourceHash', the source hash waits in 'HashesForCid'
+    SourceHashImported,
+    // the source hash is packaged under `package_key` in 'SourceTxPackage'
+    Packaged { package_key: Vec<u8> },
+    // the package is synced by 'sync_status' and removed from 'SourceTxPackage'
+    Synced,
+    Failed(String),
+}
+
+impl TransferStage {
+    pub fn is_final(&self) -> bool {
+        matches!(self, TransferStage::Signed { .. } | TransferStage::Synced | TransferStage::Failed(_))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferRecord {
+    pub cid: u32,
+    // uid for transfers relayed by 'PreparedCrossTransactionData', source hash for imported source hashes
+    pub key: Vec<u8>,
+    // source chain id of the source hash
+    pub src_chain_id: Option<u32>,
+    pub stage: TransferStage,
+    // (block number, block hash, stage) of every stage change, block number is 0 for the relay
+    pub history: Vec<(u32, Hash, TransferStage)>,
+}
+
+impl TransferRecord {
+    fn update(&mut self, block: u32, hash: Hash, stage: TransferStage) {
+        if self.stage != stage {
+            self.history.push((block, hash, stage.clone()));
+            self.stage = stage;
+        }
+    }
+}
+
+/// Correlate channel events and storage of the transfers keyed by (cid, uid or source hash).
+#[derive(Clone, Debug, Default)]
+pub struct CrossChainTracker {
+    transfers: HashMap<(u32, Vec<u8>), TransferRecord>,
+    // tx message hash -> transfer key
+    messages: HashMap<Hash, (u32, Vec<u8>)>,
+}
+
+impl CrossChainTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track the transfer relayed from `data`, keyed by (cid, uid).
+    pub fn track_transfer(&mut self, data: &PreparedCrossTransactionData) {
+        self.track((data.cid, data.uid.clone()), None);
+    }
+
+    /// Track the source hash imported by 'import_new_source_hash', keyed by (cid, source hash).
+    pub fn track_source_hash(&mut self, cid: u32, src_chain_id: u32, src_hash: Vec<u8>) {
+        self.track((cid, src_hash), Some(src_chain_id));
+    }
+
+    fn track(&mut self, key: (u32, Vec<u8>), src_chain_id: Option<u32>) {
+        self.transfers.entry(key.clone()).or_insert_with(|| TransferRecord {
+            cid: key.0,
+            key: key.1,
+            src_chain_id,
+            stage: TransferStage::Submitted,
+            history: vec![(0, Hash::zero(), TransferStage::Submitted)],
+        });
+    }
+
+    pub fn transfer(&self, cid: u32, key: &[u8]) -> Option<&TransferRecord> {
+        self.transfers.get(&(cid, key.to_vec()))
+    }
+
+    pub fn transfers(&self) -> impl Iterator<Item = &TransferRecord> {
+        self.transfers.values()
+    }
+
+    /// Stop tracking the transfers in final stage and return them.
+    pub fn remove_finished(&mut self) -> Vec<TransferRecord> {
+        let finished: Vec<_> = self.transfers.iter().filter(|(_, t)| t.stage.is_final()).map(|(k, _)| k.clone()).collect();
+        self.messages.retain(|_, key| !finished.contains(key));
+        finished.into_iter().filter_map(|key| self.transfers.remove(&key)).collect()
+    }
+
+    /// Handle the channel events of block, ie. the events sent by `EventWatcher`.
+    pub async fn handle_events(
+        &mut self,
+        sub_client: &BoolSubClient,
+        block: u32,
+        block_hash: Hash,
+        events: &[EventDetails<BoolConfig>],
+    ) -> Result<(), subxt::Error> {
+        for event in events.iter().filter(|e| e.pallet_name() == "Channel") {
+            if let Some(NewTransaction(cid, _, _, hash)) = event.as_event::<NewTransaction>()? {
+                // the tx message carries the uid of transfer
+                let Some(message) = tx_messages(sub_client, cid, hash, Some(block_hash)).await? else {
+                    continue;
+                };
+                self.on_new_transaction(block, block_hash, cid, message.txsource.uid, hash);
+            } else if let Some(SubmitTransactionSignResult(_, _, _, hash)) = event.as_event::<SubmitTransactionSignResult>()? {
+                self.on_sign_result(block, block_hash, hash);
+            } else if let Some(NewSourceHash(cid, src_hash)) = event.as_event::<NewSourceHash>()? {
+                if let Some(transfer) = self.transfers.get_mut(&(cid, src_hash)) {
+                    transfer.update(block, block_hash, TransferStage::SourceHashImported);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // tx message `hash` of transfer (cid, uid) is imported by the channel
+    fn on_new_transaction(&mut self, block: u32, block_hash: Hash, cid: u32, uid: Vec<u8>, hash: Hash) {
+        let key = (cid, uid);
+        if let Some(transfer) = self.transfers.get_mut(&key) {
+            transfer.update(block, block_hash, TransferStage::Imported { hash });
+            self.messages.insert(hash, key);
+        }
+    }
+
+    // tx message `hash` is signed by the committee
+    fn on_sign_result(&mut self, block: u32, block_hash: Hash, hash: Hash) {
+        if let Some(transfer) = self.messages.get(&hash).and_then(|key| self.transfers.get_mut(key)) {
+            transfer.update(block, block_hash, TransferStage::Signed { hash });
+        }
+    }
+
+    /// Check the storage of unfinished transfers at `at_block`, to catch the stages without events,
+    /// ie. packaging and 'sync_status' of source hashes, or tx messages dropped by the channel.
+    pub async fn refresh(&mut self, sub_client: &BoolSubClient, at_block: Option<Hash>) -> Result<(), subxt::Error> {
+        let block_hash = sub_client.block_hash_or_latest(at_block).await?;
+        let block = sub_client.client.read().await.blocks().at(block_hash).await?.number();
+        // transfers relayed by uid are only matched to their tx message by 'NewTransaction',
+        // seed the ones which missed it from the tx messages of their committee
+        let unmatched_cids = self
+            .transfers
+            .values()
+            .filter(|t| t.src_chain_id.is_none() && t.stage == TransferStage::Submitted)
+            .map(|t| t.cid)
+            .collect::<BTreeSet<_>>();
+        for cid in unmatched_cids {
+            for (hash, message) in tx_messages_of_cid(sub_client, cid, 100, block_hash).await? {
+                let submitted = self.transfer(cid, &message.txsource.uid).map_or(false, |t| t.stage == TransferStage::Submitted);
+                if submitted {
+                    self.on_new_transaction(block, block_hash, cid, message.txsource.uid, hash);
+                }
+            }
+        }
+        for transfer in self.transfers.values_mut().filter(|t| !t.stage.is_final()) {
+            let stage = match (&transfer.stage, transfer.src_chain_id) {
+                (TransferStage::Imported { hash }, _) => {
+                    match tx_messages(sub_client, transfer.cid, *hash, Some(block_hash)).await?.map(|m| m.status) {
+                        Some(TxStatus::Finished) => TransferStage::Signed { hash: *hash },
+                        Some(TxStatus::Abnormal) => TransferStage::Failed("tx message abnormal".to_string()),
+                        Some(TxStatus::Drop) => TransferStage::Failed("tx message dropped".to_string()),
+                        _ => continue,
+                    }
+                }
+                (TransferStage::Packaged { package_key }, _) => {
+                    match source_tx_package(sub_client, transfer.cid, package_key.clone(), Some(block_hash)).await? {
+                        Some(_) => continue,
+                        None => TransferStage::Synced,
+                    }
+                }
+                (_, Some(src_chain_id)) => {
+                    match source_hash_to_package_key(sub_client, src_chain_id, transfer.key.clone(), Some(block_hash)).await? {
+                        Some(package_key) => TransferStage::Packaged { package_key },
+                        None => {
+                            let queued = hashes_for_cid(sub_client, transfer.cid, Some(block_hash))
+                                .await?
+                                .map_or(false, |(infos, _)| infos.iter().any(|info| info.src_hash == transfer.key));
+                            if !queued {
+                                continue;
+                            }
+                            TransferStage::SourceHashImported
+                        }
+                    }
+                }
+                _ => continue,
+            };
+            transfer.update(block, block_hash, stage);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_transfer_stages() {
+    let mut tracker = CrossChainTracker::new();
+    let (cid, uid, hash) = (7, b"uid".to_vec(), Hash::repeat_byte(0x11));
+    let (block_hash, other_hash) = (Hash::repeat_byte(1), Hash::repeat_byte(0x22));
+    tracker.track((cid, uid.clone()), None);
+    assert_eq!(tracker.transfer(cid, &uid).unwrap().stage, TransferStage::Submitted);
+
+    // events of other transfers are ignored
+    tracker.on_new_transaction(10, block_hash, cid, b"other".to_vec(), other_hash);
+    tracker.on_new_transaction(10, block_hash, cid + 1, uid.clone(), other_hash);
+    tracker.on_sign_result(10, block_hash, hash);
+    assert_eq!(tracker.transfer(cid, &uid).unwrap().stage, TransferStage::Submitted);
+
+    tracker.on_new_transaction(11, block_hash, cid, uid.clone(), hash);
+    assert_eq!(tracker.transfer(cid, &uid).unwrap().stage, TransferStage::Imported { hash });
+    assert!(tracker.remove_finished().is_empty());
+
+    tracker.on_sign_result(12, block_hash, other_hash);
+    assert_eq!(tracker.transfer(cid, &uid).unwrap().stage, TransferStage::Imported { hash });
+    tracker.on_sign_result(12, block_hash, hash);
+    let transfer = tracker.transfer(cid, &uid).unwrap();
+    assert_eq!(transfer.stage, TransferStage::Signed { hash });
+    assert!(transfer.stage.is_final());
+    assert_eq!(
+        transfer.history,
+        vec![
+            (0, Hash::zero(), TransferStage::Submitted),
+            (11, block_hash, TransferStage::Imported { hash }),
+            (12, block_hash, TransferStage::Signed { hash }),
+        ]
+    );
+
+    let finished = tracker.remove_finished();
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].key, uid);
+    assert!(tracker.transfer(cid, &uid).is_none());
+    assert!(tracker.messages.is_empty());
+}
@@ -11,7 +11,7 @@ pub struct SyncStatus {
     pub highest_block: Option<i64>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PreparedCrossTransactionData {
     pub channel_id: u32,
     pub cid: u32,
@@ -23,12 +23,96 @@ pub struct PreparedCrossTransactionData {
     pub amount: Vec<u8>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ImportSourceHashData {
+    pub cid: u32,
+    pub hash: Vec<u8>,
+    pub src_chain_id: u32,
+    pub uid: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PackageData {
+    pub cid: u32,
+    pub package_key: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RefreshResultData {
+    pub cid: u32,
+    pub inscription_tx: Vec<u8>,
+    pub inscription_pos: u8,
+    pub sender_pk: Vec<u8>,
+    pub sender_sig: Vec<u8>,
+    pub cmt_sig: Vec<u8>,
+    pub fork_id: u8,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct XudtSignResultData {
+    pub cid: u32,
+    pub args_of_token: Vec<u8>,
+    pub pk: Vec<u8>,
+    pub sig: Vec<u8>,
+    pub fork_id: u8,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UidSignResultData {
+    pub cid: u32,
+    pub uid: Vec<u8>,
+    pub pk: Vec<u8>,
+    pub sig: Vec<u8>,
+    pub fork_id: u8,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ForcedWithdrawalResultData {
+    pub cid: u32,
+    pub tx_nonce: u128,
+    pub sender_pk: Vec<u8>,
+    pub sender_sig: Vec<u8>,
+    pub cmt_sig: Vec<u8>,
+    pub fork_id: u8,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UpdateAssetsData {
+    pub cid: u32,
+    pub block_number: u32,
+    pub btc_asset: u128,
+    pub brc20_assets: Vec<(Vec<u8>, u128)>,
+    pub sender_pk: Vec<u8>,
+    pub sender_sig: Vec<u8>,
+    pub cmt_sig: Vec<u8>,
+    pub fork_id: u8,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ExtrinsicData {
+    // 'channel.import_new_tx'
     PreparedCrossTransaction(PreparedCrossTransactionData),
+    // 'channel.import_new_source_hash'
+    ImportSourceHash(ImportSourceHashData),
+    // 'channel.sync_status'
+    SyncStatus(PackageData),
+    // 'channel.clear_target_package'
+    ClearPackage(PackageData),
+    // 'channel.submit_refresh_result'
+    SubmitRefreshResult(RefreshResultData),
+    // 'channel.submit_issue_xudt_sign_result'
+    SubmitIssueXudtSignResult(XudtSignResultData),
+    // 'channel.submit_uid_sign_result'
+    SubmitUidSignResult(UidSignResultData),
+    // 'channel.finish_forced_withdrawal'
+    FinishForcedWithdrawal(ForcedWithdrawalResultData),
+    // 'committee_assets.update_assets'
+    UpdateAssets(UpdateAssetsData),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NeedSignedExtrinsic {
     pub id: u32,
     pub data: ExtrinsicData,
@@ -23,6 +23,29 @@ pub const REPORT_RESULT_SELECTOR: [u8; 4] = [118, 72, 134, 178];
 pub const SUBMIT_TRANSACTION_SELECTOR: [u8; 4] = [58, 164, 61, 2];
 /// keccak_256("joinOrExitServiceUnsigned(bytes[],uint256,bytes[],bytes[])".as_bytes())[..4]
 pub const JOIN_OR_EXIT_SERVICE_UNSIGNED_SELECTOR: [u8; 4] = [99, 254, 70, 76];
+/// keccak_256("importNewSourceHash(uint256,bytes,uint256,bytes)".as_bytes())[..4]
+pub const IMPORT_NEW_SOURCE_HASH_SELECTOR: [u8; 4] = [157, 25, 99, 165];
+/// keccak_256("syncStatus(uint256,bytes)".as_bytes())[..4]
+pub const SYNC_STATUS_SELECTOR: [u8; 4] = [140, 173, 139, 158];
+/// keccak_256("submitRefreshResult(uint256,bytes,uint256,bytes,bytes,bytes,uint256)".as_bytes())[..4]
+pub const SUBMIT_REFRESH_RESULT_SELECTOR: [u8; 4] = [168, 143, 201, 155];
+/// keccak_256("submitUidSignResult(uint256,bytes,bytes,bytes,uint256,bytes)".as_bytes())[..4]
+pub const SUBMIT_UID_SIGN_RESULT_SELECTOR: [u8; 4] = [3, 28, 183, 153];
+/// keccak_256("reportChange(bytes,bytes,uint256,uint256,uint256,bytes,bytes)".as_bytes())[..4]
+pub const REPORT_CHANGE_SELECTOR: [u8; 4] = [210, 181, 227, 156];
+/// keccak_256("updateAssets(uint256,uint256,uint256,(bytes,uint256)[],bytes,bytes,bytes,uint256)".as_bytes())[..4]
+pub const UPDATE_ASSETS_SELECTOR: [u8; 4] = [109, 163, 135, 222];
+
+// addresses of the Bool precompile set, mining and channel are the ones called by
+// 'join_or_exit_service_unsigned_by_evm' and 'report_result_by_evm', all are checked by 'test_precompile_addresses'.
+/// mining precompile contract address
+pub const MINING_PRECOMPILE_ADDRESS: u64 = 1101;
+/// committee precompile contract address
+pub const COMMITTEE_PRECOMPILE_ADDRESS: u64 = 1103;
+/// channel precompile contract address
+pub const CHANNEL_PRECOMPILE_ADDRESS: u64 = 1104;
+/// committee assets precompile contract address
+pub const COMMITTEE_ASSETS_PRECOMPILE_ADDRESS: u64 = 1105;
 
 pub async fn call_register_v2(
     sub_client: &BoolSubClient,
@@ -119,51 +142,7 @@ pub async fn report_result_by_evm(
         .write(UnboundedBytes::from(signature));
 
     let input = writer.build();
-
-    let chain_id = evm_chain_id(sub_client, None)
-        .await
-        .map_err(|e| e.to_string())?
-        .ok_or("get evm chain failed".to_string())?;
-    let tx = ethereum::EIP1559TransactionMessage {
-        chain_id,
-        nonce: sp_core::U256::from(0u128),
-        max_priority_fee_per_gas: sp_core::U256::from(1500000000u128),
-        max_fee_per_gas: sp_core::U256::from(4500000000u128),
-        gas_limit: sp_core::U256::from(50000000u128),
-        action: ethereum::TransactionAction::Call(H160::from_low_u64_be(1104)),
-        value: sp_core::U256::from(0u128),
-        input,
-        access_list: Default::default(),
-    };
-    let transaction = Transaction::EIP1559(EIP1559Transaction {
-        chain_id,
-        nonce: crate::bool::runtime_types::primitive_types::U256(
-            tx.nonce.0,
-        ),
-        max_priority_fee_per_gas: crate::bool::runtime_types::primitive_types::U256(
-            tx.max_priority_fee_per_gas.0
-        ),
-        max_fee_per_gas: crate::bool::runtime_types::primitive_types::U256(
-            tx.max_fee_per_gas.0
-        ),
-        gas_limit: crate::bool::runtime_types::primitive_types::U256(
-            tx.gas_limit.0
-        ),
-        // channel precompile contract address
-        action: TransactionAction::Call(H160::from_low_u64_be(1104)),
-        value: crate::bool::runtime_types::primitive_types::U256(
-            tx.value.0
-        ),
-        input: tx.input,
-        access_list: vec![],
-        odd_y_parity: Default::default(),
-        r: H256(
-            Default::default()
-        ),
-        s: H256(
-            Default::default()
-        ),
-    });
+    let transaction = build_unsigned_evm_transaction(sub_client, CHANNEL_PRECOMPILE_ADDRESS, input).await?;
 
     if call_bytes {
         transact_unsigned_call_bytes(sub_client, transaction).await
@@ -189,7 +168,20 @@ pub async fn join_or_exit_service_unsigned_by_evm(
         .write(UnboundedBytes::from(signature));
 
     let input = writer.build();
+    let transaction = build_unsigned_evm_transaction(sub_client, MINING_PRECOMPILE_ADDRESS, input).await?;
 
+    transact_unsigned(sub_client, transaction)
+        .await
+        .map(|hash| "0x".to_string() + &hex::encode(hash.0))
+}
+
+/// Build an unsigned EIP-1559 tx which calls the Bool precompile at `precompile` with `input`,
+/// it should be submitted by `ethereum.transact_unsigned`.
+pub async fn build_unsigned_evm_transaction(
+    sub_client: &BoolSubClient,
+    precompile: u64,
+    input: Vec<u8>,
+) -> Result<Transaction, String> {
     let chain_id = evm_chain_id(sub_client, None)
         .await
         .map_err(|e| e.to_string())?
@@ -200,13 +192,12 @@ pub async fn join_or_exit_service_unsigned_by_evm(
         max_priority_fee_per_gas: sp_core::U256::from(1500000000u128),
         max_fee_per_gas: sp_core::U256::from(4500000000u128),
         gas_limit: sp_core::U256::from(50000000u128),
-        // mining precompile contract address
-        action: ethereum::TransactionAction::Call(H160::from_low_u64_be(1101)),
+        action: ethereum::TransactionAction::Call(H160::from_low_u64_be(precompile)),
         value: sp_core::U256::from(0u128),
         input,
         access_list: Default::default(),
     };
-    let transaction = Transaction::EIP1559(EIP1559Transaction {
+    Ok(Transaction::EIP1559(EIP1559Transaction {
         chain_id,
         nonce: crate::bool::runtime_types::primitive_types::U256(
             tx.nonce.0,
@@ -220,7 +211,7 @@ pub async fn join_or_exit_service_unsigned_by_evm(
         gas_limit: crate::bool::runtime_types::primitive_types::U256(
             tx.gas_limit.0
         ),
-        action: TransactionAction::Call(H160::from_low_u64_be(1101)),
+        action: TransactionAction::Call(H160::from_low_u64_be(precompile)),
         value: crate::bool::runtime_types::primitive_types::U256(
             tx.value.0
         ),
@@ -233,11 +224,7 @@ pub async fn join_or_exit_service_unsigned_by_evm(
         s: H256(
             Default::default()
         ),
-    });
-
-    transact_unsigned(sub_client, transaction)
-        .await
-        .map(|hash| "0x".to_string() + &hex::encode(hash.0))
+    }))
 }
 
 pub async fn query_current_block_number(sub_client: &BoolSubClient) -> Result<u32, String> {
@@ -251,3 +238,21 @@ pub async fn query_current_block_number(sub_client: &BoolSubClient) -> Result<u3
         .map(|block| block.number())
         .map_err(|e| e.to_string())
 }
+
+#[tokio::test]
+async fn test_precompile_addresses() {
+    use crate::query::ethereum::evm_call;
+    use sp_core::U256;
+
+    let url = "wss://test-rpc-node-ws.bool.network".to_string();
+    let client = &crate::client::SubClient::new_from_signer(&url, None, None, None).await.unwrap();
+    let call = move |to: u64, selector: [u8; 4]| {
+        evm_call(client, H160::zero(), H160::from_low_u64_be(to), selector.to_vec(), U256::zero(), U256::from(500000u64), None)
+    };
+    // a call without code succeeds with empty output, a precompile reverts the selector without arguments
+    assert_eq!(call(0xdead, SYNC_STATUS_SELECTOR).await.unwrap(), Vec::<u8>::new());
+    assert!(call(MINING_PRECOMPILE_ADDRESS, JOIN_OR_EXIT_SERVICE_UNSIGNED_SELECTOR).await.is_err());
+    assert!(call(COMMITTEE_PRECOMPILE_ADDRESS, REPORT_CHANGE_SELECTOR).await.is_err());
+    assert!(call(CHANNEL_PRECOMPILE_ADDRESS, SYNC_STATUS_SELECTOR).await.is_err());
+    assert!(call(COMMITTEE_ASSETS_PRECOMPILE_ADDRESS, UPDATE_ASSETS_SELECTOR).await.is_err());
+}